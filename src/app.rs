@@ -1,15 +1,21 @@
 use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync;
 
 use anyhow::Context;
 use arboard::Clipboard;
-use egui::Key;
+use egui::text::{CCursor, CCursorRange, LayoutJob};
+use egui::{TextFormat, TextStyle};
 use mlua::Lua;
 
-use crate::config::{get_scripts, Config};
-use crate::search::{ResultAction, Search, SearchResult};
+use crate::config::{self, get_scripts, Config};
+use crate::icons::{IconSource, Icons};
+use crate::keys::{Bindings, Command};
+use crate::preview::Previewer;
+use crate::search::{ResultAction, Search, SearchMode, SearchResult};
 use crate::util::get_shortcuts;
+use crate::window::{self, Monitor};
 
 #[derive(Clone, Copy, Debug)]
 pub enum HotkeyEvent {
@@ -18,7 +24,8 @@ pub enum HotkeyEvent {
 
 #[derive(Clone, Debug)]
 pub enum LuaShortcutEvent {
-    Add(String),
+    // name, description and markdown for the preview panel
+    Add(String, Option<String>, Option<String>),
     Done,
 }
 
@@ -34,23 +41,179 @@ pub struct AppChannels {
     lua_close_rx: sync::mpsc::Receiver<LuaEvent>,
 }
 
+// how far PageUp/PageDown move the selection
+const PAGE_SIZE: usize = 8;
+// of the window, when the preview panel is on
+const PREVIEW_WIDTH: f32 = 0.4;
+
 #[derive(Default, Clone, Debug)]
 struct Opened {
     input: String,
-    focused: Option<usize>,
-    items: usize,
+    selected: usize,
+    // set once the user moves the selection, so it sticks to that entry while typing
+    selected_text: Option<String>,
+    scroll_to_selected: bool,
+    history_pos: Option<usize>,
+    menu: Option<ActionsMenu>,
+    // the input was replaced (history, tab completion), so move the cursor after it
+    cursor_to_end: bool,
+    // how tall everything drawn on the last frame was, for auto_height
+    content_height: Option<f32>,
+    choosing_app: Option<AppChooser>,
+}
+
+// the actions menu for the selected result, opened with run-secondary
+#[derive(Clone, Debug)]
+struct ActionsMenu {
+    text: String,
+    selected: usize,
+}
+
+// "open with..." swaps the results for the apps to pick from
+#[derive(Clone, Debug)]
+struct AppChooser {
+    path: PathBuf,
+    // put back when it's closed
+    query: String,
 }
+
 impl Opened {
-    pub fn cycle_focus(&mut self) {
-        self.focused = match self.focused {
-            Some(n) => {
-                if n + 1 == self.items {
-                    None
-                } else {
-                    Some(n + 1)
-                }
+    fn sync_selection(&mut self, results: &[SearchResult]) {
+        let kept = self
+            .selected_text
+            .as_ref()
+            .and_then(|text| results.iter().position(|result| &result.text == text));
+
+        match kept {
+            Some(pos) => self.selected = pos,
+            None => {
+                self.selected_text = None;
+                // skip over calculator errors and the like
+                self.selected = results
+                    .iter()
+                    .position(|result| result.action().is_some())
+                    .unwrap_or(0);
+            }
+        }
+
+        let selected_text = results.get(self.selected).map(|result| &result.text);
+        if self.menu.as_ref().map(|menu| &menu.text) != selected_text {
+            self.menu = None;
+        }
+    }
+
+    fn open_menu(&mut self, results: &[SearchResult]) {
+        if let Some(result) = results.get(self.selected) {
+            if !result.actions.is_empty() {
+                self.menu = Some(ActionsMenu {
+                    text: result.text.clone(),
+                    selected: 0,
+                });
+            }
+        }
+    }
+
+    // keys move around the actions menu while it's open, returns the action to run
+    fn menu_command(&mut self, command: Command, results: &[SearchResult]) -> Option<usize> {
+        let count = results
+            .get(self.selected)
+            .map_or(0, |result| result.actions.len());
+        let menu = self.menu.as_mut()?;
+
+        match command {
+            Command::Next => menu.selected = (menu.selected + 1) % count.max(1),
+            Command::Previous => {
+                menu.selected = menu
+                    .selected
+                    .checked_sub(1)
+                    .unwrap_or(count.saturating_sub(1))
+            }
+            Command::Run => {
+                let selected = menu.selected;
+                self.menu = None;
+                return Some(selected);
             }
-            None => Some(0),
+            Command::Close | Command::RunSecondary => self.menu = None,
+            _ => {}
+        }
+
+        None
+    }
+
+    fn select(&mut self, pos: usize, results: &[SearchResult]) {
+        if results.is_empty() {
+            return;
+        }
+
+        self.selected = pos.min(results.len() - 1);
+        self.selected_text = Some(results[self.selected].text.clone());
+        self.scroll_to_selected = true;
+    }
+
+    // walks back through previously run queries, starting over once the input is edited
+    fn history_up(&mut self, history: &[String]) {
+        let pos = match self.history_pos {
+            Some(pos) if history.get(pos) == Some(&self.input) => pos.saturating_sub(1),
+            _ => match history.len().checked_sub(1) {
+                Some(last) => last,
+                None => return,
+            },
+        };
+
+        self.history_pos = Some(pos);
+        self.set_input(history[pos].clone());
+    }
+
+    // like typing it, so the selection starts over on the new results
+    fn set_input(&mut self, input: String) {
+        self.input = input;
+        self.selected = 0;
+        self.selected_text = None;
+        self.menu = None;
+        self.cursor_to_end = true;
+    }
+
+    fn choose_app(&mut self, path: &Path) {
+        self.menu = None;
+        self.choosing_app = Some(AppChooser {
+            path: path.to_path_buf(),
+            query: std::mem::take(&mut self.input),
+        });
+        self.set_input(String::new());
+    }
+
+    // back to the results from before "open with...", false if it wasn't open
+    fn stop_choosing_app(&mut self) -> bool {
+        match self.choosing_app.take() {
+            Some(chooser) => {
+                self.set_input(chooser.query);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // what goes in the query history, not the app name typed into the chooser
+    fn query(&self) -> &str {
+        match &self.choosing_app {
+            Some(chooser) => &chooser.query,
+            None => &self.input,
+        }
+    }
+
+    fn select_next(&mut self, results: &[SearchResult]) {
+        if self.selected + 1 >= results.len() {
+            self.select(0, results);
+        } else {
+            self.select(self.selected + 1, results);
+        }
+    }
+
+    fn select_previous(&mut self, results: &[SearchResult]) {
+        if self.selected == 0 {
+            self.select(results.len().saturating_sub(1), results);
+        } else {
+            self.select(self.selected - 1, results);
         }
     }
 }
@@ -66,14 +229,22 @@ pub struct App {
     search: Search,
     state: AppState,
     app_channels: AppChannels,
+    bindings: Bindings,
+    icons: Icons,
+    previewer: Previewer,
+    // the one it was last opened on
+    monitor: Option<Monitor>,
+    window_size: Option<egui::Vec2>,
     _hotkey_thread: std::thread::JoinHandle<()>,
-    _config: Config,
+    config: Config,
 }
 
 impl App {
     pub fn new(ctx: egui::Context, config: Config) -> Self {
         let shortcuts = get_shortcuts(&config);
-        let mut search = Search::new(shortcuts, config.search.aliases.clone());
+        let mut search = Search::new(shortcuts, &config, ctx.clone());
+        let icons = Icons::new(ctx.clone());
+        let previewer = Previewer::new(ctx.clone());
 
         let (events_tx, hotkeys_rx) = sync::mpsc::channel();
         let hotkey_thread = std::thread::spawn({
@@ -132,14 +303,48 @@ impl App {
                 .unwrap();
             lua_table.set("copy", copy).unwrap();
 
+            lua.set_named_registry_value("custom_descriptions", HashMap::<String, String>::new())
+                .unwrap();
+            lua.set_named_registry_value("custom_previews", HashMap::<String, String>::new())
+                .unwrap();
+
+            // tistow.add_entry(name, func, description, preview), the description and the
+            // markdown for the preview panel are optional
             let add_entry = lua
                 .create_function(
-                    |lua, (name, func): (String, mlua::Function)| -> mlua::Result<()> {
+                    |lua,
+                     (name, func, description, preview): (
+                        String,
+                        mlua::Function,
+                        Option<String>,
+                        Option<String>,
+                    )|
+                     -> mlua::Result<()> {
                         let mut custom_shortcuts: HashMap<String, mlua::Function> =
                             lua.named_registry_value("custom_shortcuts").unwrap();
+                        let mut custom_descriptions: HashMap<String, String> =
+                            lua.named_registry_value("custom_descriptions").unwrap();
+                        let mut custom_previews: HashMap<String, String> =
+                            lua.named_registry_value("custom_previews").unwrap();
+
+                        match description {
+                            Some(description) => {
+                                custom_descriptions.insert(name.clone(), description)
+                            }
+                            None => custom_descriptions.remove(&name),
+                        };
+                        match preview {
+                            Some(preview) => custom_previews.insert(name.clone(), preview),
+                            None => custom_previews.remove(&name),
+                        };
                         custom_shortcuts.insert(name, func);
+
                         lua.set_named_registry_value("custom_shortcuts", custom_shortcuts)
                             .unwrap();
+                        lua.set_named_registry_value("custom_descriptions", custom_descriptions)
+                            .unwrap();
+                        lua.set_named_registry_value("custom_previews", custom_previews)
+                            .unwrap();
 
                         Ok(())
                     },
@@ -157,8 +362,16 @@ impl App {
 
             let custom_shortcuts: HashMap<String, mlua::Function> =
                 lua.named_registry_value("custom_shortcuts").unwrap();
+            let mut custom_descriptions: HashMap<String, String> =
+                lua.named_registry_value("custom_descriptions").unwrap();
+            let mut custom_previews: HashMap<String, String> =
+                lua.named_registry_value("custom_previews").unwrap();
             for (name, _) in custom_shortcuts {
-                shortcuts_tx.send(LuaShortcutEvent::Add(name)).unwrap();
+                let description = custom_descriptions.remove(&name);
+                let preview = custom_previews.remove(&name);
+                shortcuts_tx
+                    .send(LuaShortcutEvent::Add(name, description, preview))
+                    .unwrap();
             }
             shortcuts_tx.send(LuaShortcutEvent::Done).unwrap();
 
@@ -186,8 +399,8 @@ impl App {
 
         loop {
             match shortcuts_rx.recv() {
-                Ok(LuaShortcutEvent::Add(name)) => {
-                    search.add_custom_shortcut(name);
+                Ok(LuaShortcutEvent::Add(name, description, preview)) => {
+                    search.add_custom_shortcut(name, description, preview);
                 }
                 Ok(LuaShortcutEvent::Done) => {
                     break;
@@ -208,17 +421,35 @@ impl App {
                 lua_close_rx: close_rx,
             },
 
+            bindings: Bindings::new(&config.keys).expect("invalid key binding"),
+            icons,
+            previewer,
+            monitor: window::active_monitor(config.window.monitor),
+            window_size: None,
+
             _hotkey_thread: hotkey_thread,
-            _config: config,
+            config,
         }
     }
 
-    fn handle_select(selection: &SearchResult, app_channels: &AppChannels) -> anyhow::Result<bool> {
-        println!("select: {}", selection.text);
-        let action = match &selection.action {
-            Some(action) => action,
-            _ => return Ok(false),
-        };
+    fn handle_select(
+        selection: &SearchResult,
+        search: &Search,
+        app_channels: &AppChannels,
+    ) -> anyhow::Result<bool> {
+        match selection.action() {
+            Some(action) => Self::run_action(action, selection, search, app_channels),
+            None => Ok(false),
+        }
+    }
+
+    fn run_action(
+        action: &ResultAction,
+        selection: &SearchResult,
+        search: &Search,
+        app_channels: &AppChannels,
+    ) -> anyhow::Result<bool> {
+        println!("select: {} ({})", selection.text, action.label());
 
         let should_close = match action {
             ResultAction::Open { path } => {
@@ -226,13 +457,88 @@ impl App {
 
                 true
             }
+            ResultAction::Run {
+                path,
+                args,
+                working_dir,
+            } => {
+                crate::util::run_with_args(path, args, working_dir.as_deref())
+                    .context("couldn't spawn process")?;
+
+                true
+            }
+            ResultAction::OpenWith { path, app } => {
+                open::with(path, app).context("couldn't spawn process")?;
+
+                true
+            }
+            // changes what's listed, see run_menu_action
+            ResultAction::ChooseApp { .. } => false,
+            ResultAction::OpenElevated { path } => {
+                // a declined UAC prompt shouldn't take the launcher down with it
+                match crate::util::open_elevated(path, search.elevate_with()) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        println!("couldn't launch elevated: {}", e);
+                        false
+                    }
+                }
+            }
+            ResultAction::RevealInFolder { path } => {
+                crate::util::reveal_in_folder(path).context("couldn't open folder")?;
+
+                true
+            }
             ResultAction::Copy { text } => {
                 Clipboard::new()?
                     .set_text(text.to_string())
                     .context("couldn't copy to clipboard")?;
 
+                if let SearchMode::Calculator = selection.mode {
+                    search.commit_calculation(&selection.text);
+                }
+
+                false
+            }
+            ResultAction::CopyPath { path } => {
+                Clipboard::new()?
+                    .set_text(path.display().to_string())
+                    .context("couldn't copy to clipboard")?;
+
+                false
+            }
+            ResultAction::ClearHistory => {
+                search.clear_calculator_history();
+
+                false
+            }
+            ResultAction::RunShell { .. } => {
+                // stay open to show the output
+                if let Err(e) = search.run_shell(action) {
+                    println!("couldn't run command: {}", e);
+                }
+
+                false
+            }
+            ResultAction::CopyOutput => {
+                Clipboard::new()?
+                    .set_text(search.shell_output())
+                    .context("couldn't copy to clipboard")?;
+
+                false
+            }
+            ResultAction::KillShell => {
+                if let Err(e) = search.kill_shell() {
+                    println!("couldn't stop command: {}", e);
+                }
+
                 false
             }
+            ResultAction::RunShellDetached { .. } | ResultAction::RunInTerminal { .. } => {
+                search.run_shell(action).context("couldn't run command")?;
+
+                true
+            }
             ResultAction::Lua => {
                 // ghelp
                 app_channels
@@ -243,9 +549,63 @@ impl App {
                 false
             }
         };
+
+        // for the launch count in the preview panel
+        let launched = matches!(
+            action,
+            ResultAction::Open { .. }
+                | ResultAction::Run { .. }
+                | ResultAction::OpenWith { .. }
+                | ResultAction::OpenElevated { .. }
+        );
+        if launched && should_close {
+            search.record_launch(selection);
+        }
+
         Ok(should_close)
     }
 
+    // an entry picked from the actions menu, returns whether to close
+    fn run_menu_action(
+        opened: &mut Opened,
+        action: &ResultAction,
+        selection: &SearchResult,
+        search: &Search,
+        app_channels: &AppChannels,
+    ) -> anyhow::Result<bool> {
+        opened.menu = None;
+        if let ResultAction::ChooseApp { path } = action {
+            opened.choose_app(path);
+            return Ok(false);
+        }
+
+        search.remember_query(opened.query());
+        Self::run_action(action, selection, search, app_channels)
+    }
+
+    // copy-path, open-folder and run-elevated pick the matching entry from the actions menu
+    fn run_command(
+        command: Command,
+        selection: &SearchResult,
+        search: &Search,
+        app_channels: &AppChannels,
+    ) -> anyhow::Result<bool> {
+        let action = selection.actions.iter().find(|action| {
+            matches!(
+                (command, action),
+                (Command::CopyPath, ResultAction::CopyPath { .. })
+                    | (Command::OpenFolder, ResultAction::RevealInFolder { .. })
+                    | (Command::RunElevated, ResultAction::OpenElevated { .. })
+            )
+        });
+
+        match (command, action) {
+            (Command::Run, _) => Self::handle_select(selection, search, app_channels),
+            (_, Some(action)) => Self::run_action(action, selection, search, app_channels),
+            _ => Ok(false),
+        }
+    }
+
     fn get_new_state(&self, ctx: &egui::Context) -> anyhow::Result<AppState> {
         match &self.state {
             AppState::First => Ok(AppState::Unopened),
@@ -256,32 +616,202 @@ impl App {
 
     fn process_opened(&self, opened: &Opened, ctx: &egui::Context) -> anyhow::Result<AppState> {
         let mut opened = opened.clone();
-        let results = self.search.search(&opened.input);
-        opened.items = results.len();
+        let results = match &opened.choosing_app {
+            Some(chooser) => self.search.app_choices(&chooser.path, &opened.input),
+            None => self.search.search(&opened.input),
+        };
+        opened.sync_selection(&results);
 
-        //println!("{}", self.focused);
-        if ctx.input().key_pressed(egui::Key::Tab) {
-            opened.cycle_focus();
+        // keep drawing while a `>` command streams output in
+        if self.search.is_busy() {
+            ctx.request_repaint();
         }
-        if ctx.input().key_released(Key::Escape) {
-            return Ok(AppState::Unopened);
+
+        let commands = self.bindings.pressed(&mut ctx.input_mut());
+        let input = opened.input.clone();
+        for command in commands {
+            // `results` are for the old input, so anything after it would act on those
+            if opened.input != input {
+                break;
+            }
+            if opened.menu.is_some() {
+                let index = opened.menu_command(command, &results);
+                let result = results.get(opened.selected);
+                if let Some(result) = result {
+                    if let Some(action) = index.and_then(|i| result.actions.get(i)) {
+                        let search = &self.search;
+                        let app_channels = &self.app_channels;
+                        if Self::run_menu_action(&mut opened, action, result, search, app_channels)?
+                        {
+                            return Ok(AppState::Unopened);
+                        }
+                    }
+                }
+                continue;
+            }
+
+            match command {
+                Command::Next => opened.select_next(&results),
+                Command::Complete => {
+                    let completion = results
+                        .get(opened.selected)
+                        .and_then(|result| self.search.completion(result))
+                        .filter(|completion| *completion != opened.input);
+                    match completion {
+                        Some(completion) => opened.set_input(completion),
+                        None => opened.select_next(&results),
+                    }
+                }
+                Command::Previous => opened.select_previous(&results),
+                Command::PageDown => opened.select(opened.selected + PAGE_SIZE, &results),
+                Command::PageUp => {
+                    opened.select(opened.selected.saturating_sub(PAGE_SIZE), &results)
+                }
+                Command::First => opened.select(0, &results),
+                Command::Last => opened.select(results.len().saturating_sub(1), &results),
+                Command::Close => {
+                    if !opened.stop_choosing_app() {
+                        return Ok(AppState::Unopened);
+                    }
+                }
+                Command::ClearQuery => opened.set_input(String::new()),
+                Command::HistoryUp => opened.history_up(&self.search.query_history()),
+                Command::ToggleHidden => self.search.toggle_hidden_files(),
+                Command::RunSecondary => opened.open_menu(&results),
+                Command::Run | Command::RunElevated | Command::CopyPath | Command::OpenFolder => {
+                    if let Some(result) = results.get(opened.selected) {
+                        self.search.remember_query(opened.query());
+                        if Self::run_command(command, result, &self.search, &self.app_channels)? {
+                            return Ok(AppState::Unopened);
+                        }
+                    }
+                }
+            }
+        }
+
+        if self.config.style.preview {
+            let width = ctx.available_rect().width() * PREVIEW_WIDTH;
+            egui::SidePanel::right("preview")
+                .resizable(false)
+                .width_range(width..=width)
+                .show(ctx, |ui| {
+                    self.previewer
+                        .show(ui, &self.search, results.get(opened.selected))
+                });
         }
 
         egui::CentralPanel::default()
             .show(ctx, |ui| {
-                Self::draw_opened_central(ui, opened, results, &self.app_channels)
+                Self::draw_opened_central(
+                    ui,
+                    opened,
+                    results,
+                    &self.search,
+                    &self.app_channels,
+                    &self.icons,
+                    &self.config.style,
+                )
             })
             .inner
     }
 
+    // the text with the matched characters highlighted, and its subtitle dimmed on a
+    // second line unless rows are compact
+    fn result_text(
+        ui: &egui::Ui,
+        result: &SearchResult,
+        style: &config::Style,
+    ) -> egui::WidgetText {
+        let highlight_color = match &style.highlight_color {
+            Some(color) => crate::util::hex_to_color32(color),
+            None => ui.visuals().hyperlink_color,
+        };
+
+        let append = |job: &mut LayoutJob, text: &str, highlighted: bool| {
+            let color = if highlighted {
+                highlight_color
+            } else {
+                ui.visuals().text_color()
+            };
+            let font_id = TextStyle::Button.resolve(ui.style());
+            job.append(text, 0.0, TextFormat::simple(font_id, color));
+        };
+
+        // runs of highlighted and plain characters
+        let mut job = LayoutJob::default();
+        let mut run = String::new();
+        let mut run_highlighted = false;
+        for (pos, c) in result.text.chars().enumerate() {
+            let highlighted = result.highlights.contains(&pos);
+            if highlighted != run_highlighted && !run.is_empty() {
+                append(&mut job, &std::mem::take(&mut run), run_highlighted);
+            }
+            run_highlighted = highlighted;
+            run.push(c);
+        }
+        append(&mut job, &run, run_highlighted);
+
+        let subtitle = match &result.subtitle {
+            Some(subtitle) if !style.compact => subtitle,
+            _ => return job.into(),
+        };
+        job.append(
+            &format!("\n{}", subtitle),
+            0.0,
+            TextFormat {
+                font_id: TextStyle::Small.resolve(ui.style()),
+                color: ui.visuals().weak_text_color(),
+                ..Default::default()
+            },
+        );
+
+        job.into()
+    }
+
+    // empty space while it's loading or if there's no icon, to keep the text lined up
+    fn draw_icon(ui: &mut egui::Ui, icons: &Icons, source: Option<&IconSource>, size: f32) {
+        match source.and_then(|source| icons.get(ui.ctx(), source)) {
+            Some(texture) => {
+                ui.image(texture, [size, size]);
+            }
+            None => {
+                ui.allocate_space(egui::vec2(size, size));
+            }
+        }
+    }
+
     fn draw_opened_central(
         ui: &mut egui::Ui,
         mut opened: Opened,
         results: Vec<SearchResult>,
+        search: &Search,
         app_channels: &AppChannels,
+        icons: &Icons,
+        style: &config::Style,
     ) -> anyhow::Result<AppState> {
+        let input_id = egui::Id::new("search_input");
+        if opened.cursor_to_end {
+            let mut state = egui::TextEdit::load_state(ui.ctx(), input_id).unwrap_or_default();
+            let end = CCursor::new(opened.input.chars().count());
+            state.set_ccursor_range(Some(CCursorRange::one(end)));
+            state.store(ui.ctx(), input_id);
+            opened.cursor_to_end = false;
+        }
+
+        let hint = match &opened.choosing_app {
+            Some(chooser) => format!(
+                "open {} with...",
+                chooser
+                    .path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+            ),
+            None => "search anything...".to_string(),
+        };
         let input_widget = egui::TextEdit::singleline(&mut opened.input)
-            .hint_text("search anything...")
+            .id(input_id)
+            .hint_text(hint)
             .lock_focus(true);
         let input_res = ui.add_sized((ui.available_width(), 18_f32), input_widget);
 
@@ -289,66 +819,123 @@ impl App {
             return Ok(AppState::Unopened);
         }
 
-        if ui.input().key_pressed(egui::Key::Enter) && !results.is_empty() {
-            let result = if input_res.lost_focus() {
-                // user presses enter in the input field (select first input)
-                Some(&results[0])
-            } else if opened.focused.is_some() {
-                // user selects option manually
-                Some(&results[opened.focused.unwrap()])
-            } else {
-                None
-            };
-
-            if let Some(result) = result {
-                if Self::handle_select(result, app_channels)? {
-                    return Ok(AppState::Unopened);
-                }
-            }
-        }
-
-        if opened.focused.is_none() {
-            input_res.request_focus();
-        }
+        // the input keeps focus, the selection is only drawn in the list
+        input_res.request_focus();
 
         ui.separator();
+        let list_top = ui.cursor().top();
 
         let inner = egui::ScrollArea::vertical()
             .auto_shrink([false, false])
             .min_scrolled_width(ui.available_width())
             .show(ui, |scroll_ui| {
-                if opened.input.is_empty() {
+                // the app chooser lists everything before anything is typed. otherwise
+                // there's nothing to show yet, but it stays at its full height instead of
+                // shrinking down to the input
+                if opened.input.is_empty() && opened.choosing_app.is_none() {
+                    opened.content_height = None;
                     return Ok(None);
                 }
+                // the central panel's margin, below the list
+                opened.content_height = Some(list_top + 8.0);
 
                 if opened.input.trim() == "anything" && results.is_empty() {
                     scroll_ui.label("...uh, not like that");
                 }
 
-                for (pos, result) in results.iter().enumerate() {
-                    let mut label_res = scroll_ui.selectable_label(false, &result.text);
+                // as tall as the text next to it
+                let icon_size = if style.compact { 16.0 } else { 28.0 };
+                let show_icons = results.iter().any(|result| result.icon.is_some());
 
-                    if let Some(ResultAction::Open { path }) = &result.action {
-                        label_res = label_res.on_hover_text(path.to_str().unwrap());
+                for (pos, result) in results.iter().enumerate() {
+                    let mut label_res = if result.action().is_some() {
+                        let text = Self::result_text(scroll_ui, result, style);
+                        scroll_ui
+                            .horizontal(|ui| {
+                                if show_icons {
+                                    Self::draw_icon(ui, icons, result.icon.as_ref(), icon_size);
+                                }
+                                ui.selectable_label(opened.selected == pos, text)
+                            })
+                            .inner
+                    } else {
+                        scroll_ui.label(egui::RichText::new(&result.text).weak())
+                    };
+
+                    match (&result.subtitle, result.action()) {
+                        (Some(subtitle), _) if style.compact => {
+                            label_res = label_res.on_hover_text(subtitle);
+                        }
+                        (None, Some(ResultAction::Open { path })) => {
+                            label_res = label_res.on_hover_text(path.to_str().unwrap());
+                        }
+                        _ => {}
                     }
 
-                    if opened.focused == Some(pos) {
-                        label_res.request_focus();
+                    if opened.scroll_to_selected && opened.selected == pos {
                         label_res.scroll_to_me(None);
                     }
 
+                    // show what's going to run before it does
+                    if let Some(ResultAction::Run { path, args, .. }) = result.action() {
+                        if opened.selected == pos {
+                            let command_line = crate::util::command_line(path, args);
+                            scroll_ui.label(egui::RichText::new(command_line).monospace().weak());
+                        }
+                    }
+
+                    // right click for every action, not only the first
+                    let mut clicked = None;
+                    if !result.actions.is_empty() {
+                        if label_res.secondary_clicked() {
+                            opened.select(pos, &results);
+                        }
+                        label_res = label_res.context_menu(|ui| {
+                            for action in &result.actions {
+                                if ui.button(action.label()).clicked() {
+                                    clicked = Some(action);
+                                    ui.close_menu();
+                                }
+                            }
+                        });
+                    }
+
                     if label_res.clicked() {
-                        let should_close = Self::handle_select(result, app_channels)?;
+                        search.remember_query(opened.query());
+                        let should_close = Self::handle_select(result, search, app_channels)?;
                         if should_close {
                             return Ok(Some(AppState::Unopened));
                         }
                     }
+
+                    let menu = opened.menu.as_ref().filter(|_| opened.selected == pos);
+                    if let Some(menu) = menu {
+                        scroll_ui.indent("actions", |ui| {
+                            for (i, action) in result.actions.iter().enumerate() {
+                                if ui
+                                    .selectable_label(menu.selected == i, action.label())
+                                    .clicked()
+                                {
+                                    clicked = Some(action);
+                                }
+                            }
+                        });
+                    }
+
+                    if let Some(action) = clicked {
+                        if Self::run_menu_action(&mut opened, action, result, search, app_channels)?
+                        {
+                            return Ok(Some(AppState::Unopened));
+                        }
+                    }
                 }
 
+                opened.content_height = Some(list_top + scroll_ui.min_rect().height() + 8.0);
                 anyhow::Ok(None)
             })
             .inner?;
 
+        opened.scroll_to_selected = false;
         Ok(inner.unwrap_or(AppState::Opened(opened)))
     }
 
@@ -375,11 +962,31 @@ impl eframe::App for App {
         let events: Vec<_> = self.app_channels.hotkeys_rx.try_iter().collect();
         for event in events {
             match event {
-                HotkeyEvent::Open => self.set_state(AppState::Opened(Opened::default()), frame),
+                HotkeyEvent::Open => {
+                    // the cursor or focus may be on another monitor since last time
+                    self.monitor = window::active_monitor(self.config.window.monitor);
+                    if self.config.window.position != config::Position::Absolute {
+                        window::move_to(window::position(
+                            &self.config.window,
+                            self.monitor.as_ref(),
+                        ));
+                    }
+                    self.set_state(AppState::Opened(Opened::default()), frame)
+                }
             }
         }
 
         let state = self.get_new_state(ctx);
         self.set_state(state.unwrap(), frame);
+
+        if let AppState::Opened(opened) = &self.state {
+            // the preview panel fills whatever height it gets, so it keeps the full height
+            let content_height = opened.content_height.filter(|_| !self.config.style.preview);
+            let size = window::size(&self.config.window, self.monitor.as_ref(), content_height);
+            if self.window_size != Some(size) {
+                frame.set_window_size(size);
+                self.window_size = Some(size);
+            }
+        }
     }
 }
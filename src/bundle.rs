@@ -0,0 +1,97 @@
+// reads a macos .app bundle's Contents/Info.plist (xml or binary) for its name and version
+
+use std::path::Path;
+
+#[derive(Clone, Debug, Default)]
+pub struct Bundle {
+    // CFBundleDisplayName, falling back to CFBundleName
+    pub display_name: Option<String>,
+    // e.g. "com.microsoft.VSCode"
+    pub identifier: Option<String>,
+    // CFBundleShortVersionString, falling back to CFBundleVersion
+    pub version: Option<String>,
+    // CFBundleIconFile, the .icns in Contents/Resources
+    pub icon_file: Option<String>,
+    // CFBundleExecutable, in Contents/MacOS
+    pub executable: Option<String>,
+}
+
+/// Reads the Info.plist of an .app bundle. `None` for anything else, or a bundle
+/// without one.
+pub fn read(path: &Path) -> anyhow::Result<Option<Bundle>> {
+    let is_app = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("app"));
+    let info_path = path.join("Contents").join("Info.plist");
+    if !is_app || !info_path.exists() {
+        return Ok(None);
+    }
+
+    let info = plist::Value::from_file(info_path)?;
+    let info = info
+        .as_dictionary()
+        .ok_or_else(|| anyhow::anyhow!("Info.plist isn't a dictionary"))?;
+
+    let string = |keys: &[&str]| {
+        keys.iter()
+            .filter_map(|key| info.get(key)?.as_string())
+            .map(str::trim)
+            .find(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+
+    Ok(Some(Bundle {
+        display_name: string(&["CFBundleDisplayName", "CFBundleName"]),
+        identifier: string(&["CFBundleIdentifier"]),
+        version: string(&["CFBundleShortVersionString", "CFBundleVersion"]),
+        icon_file: string(&["CFBundleIconFile"]),
+        executable: string(&["CFBundleExecutable"]),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // made by tests/fixtures/bundles/generate.py
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/bundles")
+            .join(name)
+    }
+
+    #[test]
+    fn xml_info_plist() {
+        let bundle = read(&fixture("Display.app")).unwrap().unwrap();
+        assert_eq!(bundle.display_name.as_deref(), Some("Display Name"));
+        assert_eq!(bundle.identifier.as_deref(), Some("com.example.display"));
+        assert_eq!(bundle.version.as_deref(), Some("2.1"));
+        assert_eq!(bundle.icon_file.as_deref(), Some("AppIcon"));
+        assert_eq!(bundle.executable.as_deref(), Some("display"));
+    }
+
+    #[test]
+    fn binary_info_plist() {
+        // a blank CFBundleDisplayName falls back to CFBundleName, and a missing
+        // CFBundleShortVersionString to CFBundleVersion
+        let bundle = read(&fixture("Binary.app")).unwrap().unwrap();
+        assert_eq!(bundle.display_name.as_deref(), Some("Binary Name"));
+        assert_eq!(bundle.identifier.as_deref(), Some("com.example.binary"));
+        assert_eq!(bundle.version.as_deref(), Some("7"));
+        assert_eq!(bundle.icon_file, None);
+
+        let bundle = read(&fixture("Stem.app")).unwrap().unwrap();
+        assert_eq!(bundle.display_name, None);
+        assert_eq!(bundle.identifier.as_deref(), Some("com.example.stem"));
+        assert_eq!(bundle.version, None);
+    }
+
+    #[test]
+    fn not_bundles() {
+        assert!(read(&fixture("NotABundle")).unwrap().is_none());
+        assert!(read(&fixture("Empty.app")).unwrap().is_none());
+        assert!(read(&fixture("Missing.app")).unwrap().is_none());
+        assert!(read(&fixture("Broken.app")).is_err());
+    }
+}
@@ -0,0 +1,1275 @@
+// exact expression evaluator for calculator mode
+// meval only knows f64 (0.1 + 0.2, big factorials) and doesn't understand radix
+// literals or bitwise ops, so we try this first and fall back to meval for
+// anything it can't do exactly (functions, constants, irrational powers)
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use meval::tokenizer::{Operation, Token as FloatToken};
+use meval::ContextProvider;
+use num::{BigInt, BigRational, Integer, One, Signed, ToPrimitive, Zero};
+
+use crate::config::{self, Rounding};
+use crate::currency::Rates;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(BigRational),
+    Ident(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+const OPS: [&str; 13] = [
+    "<<", ">>", "**", "+", "-", "*", "/", "%", "^", "&", "|", "~", "!",
+];
+
+// don't let a stray keystroke hang the ui
+const MAX_BITS: u64 = 1 << 20;
+// user functions calling each other (or themselves) forever
+const MAX_CALL_DEPTH: usize = 32;
+// every digit of anything bigger (~5000 of them) is too much to format and lay out on
+// every frame, those only get scientific notation
+const MAX_DIGITS_BITS: u64 = 1 << 14;
+
+/// Raised when an expression is valid but can't be evaluated exactly, so the
+/// caller should hand it to meval instead.
+#[derive(Debug)]
+struct Inexact(String);
+
+impl fmt::Display for Inexact {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Inexact {}
+
+fn inexact(reason: impl Into<String>) -> anyhow::Error {
+    anyhow::Error::new(Inexact(reason.into()))
+}
+
+pub fn parse_literal(literal: &str) -> anyhow::Result<BigRational> {
+    let cleaned = literal.replace('_', "").to_lowercase();
+    let invalid = || anyhow::anyhow!("invalid number literal `{}`", literal);
+
+    let radix = if cleaned.starts_with("0x") {
+        16
+    } else if cleaned.starts_with("0b") {
+        2
+    } else if cleaned.starts_with("0o") {
+        8
+    } else {
+        10
+    };
+
+    if radix != 10 {
+        let n = BigInt::parse_bytes(&cleaned.as_bytes()[2..], radix).ok_or_else(invalid)?;
+        return Ok(BigRational::from_integer(n));
+    }
+
+    // 1.25e-3 -> 125 * 10^(-2 - 3)
+    let (mantissa, exponent) = match cleaned.split_once('e') {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().map_err(|_| invalid())?),
+        None => (cleaned.as_str(), 0),
+    };
+    let (int_part, frac_part) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    if int_part.is_empty() && frac_part.is_empty() {
+        return Err(invalid());
+    }
+
+    let digits = BigInt::parse_bytes(format!("{}{}", int_part, frac_part).as_bytes(), 10)
+        .ok_or_else(invalid)?;
+    let scale = exponent - frac_part.len() as i64;
+    if scale.unsigned_abs() > 10_000 {
+        return Err(invalid());
+    }
+
+    let digits = BigRational::from_integer(digits);
+    if scale >= 0 {
+        Ok(digits * pow10(scale as usize))
+    } else {
+        Ok(digits / pow10(scale.unsigned_abs() as usize))
+    }
+}
+
+// tokens are paired with their (1-based) column for error messages
+fn tokenize(input: &str) -> anyhow::Result<Vec<(Token, usize)>> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        let next = chars.get(i + 1).copied().unwrap_or_default();
+
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || (c == '.' && next.is_ascii_digit()) {
+            let start = i;
+            let is_decimal = !(c == '0' && "xXbBoO".contains(next));
+
+            while i < chars.len() {
+                let c = chars[i];
+                let exponent_sign =
+                    is_decimal && (c == '+' || c == '-') && matches!(chars[i - 1], 'e' | 'E');
+
+                if c.is_ascii_alphanumeric()
+                    || c == '_'
+                    || (is_decimal && c == '.')
+                    || exponent_sign
+                {
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+
+            let literal: String = chars[start..i].iter().collect();
+            let n = parse_literal(&literal)
+                .map_err(|e| anyhow::anyhow!("{} at column {}", e, column))?;
+            tokens.push((Token::Num(n), column));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((Token::Ident(chars[start..i].iter().collect()), column));
+        } else if c == '(' {
+            tokens.push((Token::LParen, column));
+            i += 1;
+        } else if c == ')' {
+            tokens.push((Token::RParen, column));
+            i += 1;
+        } else if c == ',' {
+            tokens.push((Token::Comma, column));
+            i += 1;
+        } else {
+            let rest: String = chars[i..].iter().take(2).collect();
+            let op = OPS.iter().find(|op| rest.starts_with(*op)).ok_or_else(|| {
+                anyhow::anyhow!("unexpected character `{}` at column {}", c, column)
+            })?;
+            tokens.push((Token::Op(op), column));
+            i += op.len();
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn has_radix_literal(input: &str) -> bool {
+    input
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .any(|word| {
+            let word = word.to_lowercase();
+            word.starts_with("0x") || word.starts_with("0b") || word.starts_with("0o")
+        })
+}
+
+/// Returns true if the input uses programmer syntax (radix literals, shifts,
+/// bitwise operators), in which case results are shown in every base.
+pub fn is_programmer_expr(input: &str) -> bool {
+    if has_radix_literal(input) {
+        return true;
+    }
+
+    let tokens = match tokenize(input) {
+        Ok(tokens) => tokens,
+        Err(_) => return false,
+    };
+
+    tokens.iter().any(|(t, _)| match t {
+        Token::Op(op) => matches!(*op, "<<" | ">>" | "&" | "|" | "~"),
+        Token::Ident(ident) => ident == "xor",
+        _ => false,
+    })
+}
+
+struct Parser<'a> {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    vars: &'a HashMap<String, BigRational>,
+    // with a radix literal in the expression `^` is xor like in every programming
+    // language, and powers are `**`
+    caret_is_xor: bool,
+}
+
+// precedence climbing, loosest first:
+// |, xor, &, << >>, + -, * / %, unary - ~, ^ **, postfix !
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn unexpected(&self) -> anyhow::Error {
+        match self.tokens.get(self.pos) {
+            Some((token, column)) => {
+                anyhow::anyhow!("unexpected {} at column {}", describe(token), column)
+            }
+            None => anyhow::anyhow!("unexpected end of expression"),
+        }
+    }
+
+    fn binary_op(&self, level: usize) -> Option<&'static str> {
+        match (level, self.peek()?) {
+            (0, Token::Op("|")) => Some("|"),
+            (1, Token::Ident(ident)) if ident == "xor" => Some("xor"),
+            (1, Token::Op("^")) if self.caret_is_xor => Some("xor"),
+            (2, Token::Op("&")) => Some("&"),
+            (3, Token::Op(op)) if matches!(*op, "<<" | ">>") => Some(op),
+            (4, Token::Op(op)) if matches!(*op, "+" | "-") => Some(op),
+            (5, Token::Op(op)) if matches!(*op, "*" | "/" | "%") => Some(op),
+            _ => None,
+        }
+    }
+
+    fn expr(&mut self, level: usize) -> anyhow::Result<BigRational> {
+        if level > 5 {
+            return self.unary();
+        }
+
+        let mut lhs = self.expr(level + 1)?;
+        while let Some(op) = self.binary_op(level) {
+            self.pos += 1;
+            let rhs = self.expr(level + 1)?;
+            lhs = apply(op, lhs, rhs)?;
+        }
+
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> anyhow::Result<BigRational> {
+        match self.peek() {
+            Some(Token::Op("-")) => {
+                self.pos += 1;
+                Ok(-self.unary()?)
+            }
+            Some(Token::Op("+")) => {
+                self.pos += 1;
+                self.unary()
+            }
+            Some(Token::Op("~")) => {
+                self.pos += 1;
+                let n = to_int(&self.unary()?, "~")?;
+                Ok(BigRational::from_integer(!n))
+            }
+            _ => self.power(),
+        }
+    }
+
+    fn power(&mut self) -> anyhow::Result<BigRational> {
+        let base = self.postfix()?;
+
+        let is_power = match self.peek() {
+            Some(Token::Op("**")) => true,
+            Some(Token::Op("^")) => !self.caret_is_xor,
+            _ => false,
+        };
+        if is_power {
+            self.pos += 1;
+            // right associative, and binds tighter than unary minus on the left
+            let exp = self.unary()?;
+            return apply("^", base, exp);
+        }
+
+        Ok(base)
+    }
+
+    fn postfix(&mut self) -> anyhow::Result<BigRational> {
+        let mut n = self.atom()?;
+
+        while let Some(Token::Op("!")) = self.peek() {
+            self.pos += 1;
+            n = factorial(&n)?;
+        }
+
+        Ok(n)
+    }
+
+    fn atom(&mut self) -> anyhow::Result<BigRational> {
+        match self.peek() {
+            Some(Token::Num(n)) => {
+                let n = n.clone();
+                self.pos += 1;
+                Ok(n)
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let n = self.expr(0)?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(n),
+                    _ => {
+                        self.pos -= 1;
+                        Err(self.unexpected())
+                    }
+                }
+            }
+            Some(Token::Ident(ident)) => {
+                if let Some(value) = self.vars.get(ident) {
+                    let value = value.clone();
+                    self.pos += 1;
+                    return Ok(value);
+                }
+
+                // functions and constants are meval's job
+                Err(inexact(format!("unknown variable `{}`", ident)))
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+}
+
+fn to_int(n: &BigRational, op: &str) -> anyhow::Result<BigInt> {
+    if !n.is_integer() {
+        anyhow::bail!("`{}` needs integer operands", op);
+    }
+
+    Ok(n.to_integer())
+}
+
+fn factorial(n: &BigRational) -> anyhow::Result<BigRational> {
+    let n = to_int(n, "!")?
+        .to_u64()
+        .ok_or_else(|| anyhow::anyhow!("factorial of a negative number"))?;
+    if n > 10_000 {
+        anyhow::bail!("factorial argument too large");
+    }
+
+    let product = (2..=n).fold(BigInt::one(), |acc, i| acc * i);
+    Ok(BigRational::from_integer(product))
+}
+
+fn apply(op: &str, lhs: BigRational, rhs: BigRational) -> anyhow::Result<BigRational> {
+    match op {
+        "+" => Ok(lhs + rhs),
+        "-" => Ok(lhs - rhs),
+        "*" => Ok(lhs * rhs),
+        "/" | "%" if rhs.is_zero() => anyhow::bail!("division by zero"),
+        "/" => Ok(lhs / rhs),
+        "%" => Ok(lhs % rhs),
+        "^" => {
+            if !rhs.is_integer() {
+                return Err(inexact("non-integer exponent"));
+            }
+            if lhs.is_zero() && rhs.is_negative() {
+                anyhow::bail!("division by zero");
+            }
+
+            let base_bits = lhs.numer().bits().max(lhs.denom().bits());
+            let exp = rhs
+                .to_integer()
+                .to_i32()
+                .filter(|exp| base_bits.saturating_mul(exp.unsigned_abs() as u64) <= MAX_BITS)
+                .ok_or_else(|| anyhow::anyhow!("exponent too large"))?;
+
+            Ok(num::pow::Pow::pow(&lhs, exp))
+        }
+        "&" | "|" | "xor" | "<<" | ">>" => {
+            let lhs = to_int(&lhs, op)?;
+            let rhs = to_int(&rhs, op)?;
+
+            let n = match op {
+                "&" => lhs & rhs,
+                "|" => lhs | rhs,
+                "xor" => lhs ^ rhs,
+                _ => {
+                    let shift = rhs
+                        .to_u64()
+                        .filter(|shift| *shift <= MAX_BITS)
+                        .ok_or_else(|| anyhow::anyhow!("invalid shift amount"))?;
+
+                    if op == "<<" {
+                        lhs << shift
+                    } else {
+                        lhs >> shift
+                    }
+                }
+            };
+
+            Ok(BigRational::from_integer(n))
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Num(n) => format!("number `{}`", n),
+        Token::Ident(ident) => format!("`{}`", ident),
+        Token::Op(op) => format!("operator `{}`", op),
+        Token::LParen => "`(`".to_string(),
+        Token::RParen => "`)`".to_string(),
+        Token::Comma => "`,`".to_string(),
+    }
+}
+
+/// Reports the first unbalanced parenthesis, since both evaluators give
+/// fairly unhelpful errors for those.
+pub fn check_parens(input: &str) -> anyhow::Result<()> {
+    let mut open = Vec::new();
+
+    for (i, c) in input.chars().enumerate() {
+        match c {
+            '(' => open.push(i + 1),
+            ')' => {
+                open.pop()
+                    .ok_or_else(|| anyhow::anyhow!("unbalanced parenthesis at column {}", i + 1))?;
+            }
+            _ => {}
+        }
+    }
+
+    match open.pop() {
+        Some(column) => anyhow::bail!("unbalanced parenthesis at column {}", column),
+        None => Ok(()),
+    }
+}
+
+pub fn eval_exact(input: &str, vars: &HashMap<String, BigRational>) -> anyhow::Result<BigRational> {
+    check_parens(input)?;
+
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        pos: 0,
+        vars,
+        caret_is_xor: has_radix_literal(input),
+    };
+
+    let n = parser.expr(0)?;
+    if parser.peek().is_some() {
+        return Err(parser.unexpected());
+    }
+
+    Ok(n)
+}
+
+/// What a float expression can refer to: the session's variables, the user's
+/// functions and meval's builtins (`pi`, `sin`, ...), in that order.
+pub struct Scope<'a> {
+    vars: HashMap<String, f64>,
+    functions: &'a [Function],
+    builtins: meval::Context<'static>,
+}
+
+impl<'a> Scope<'a> {
+    pub fn new(vars: &HashMap<String, BigRational>, functions: &'a [Function]) -> Self {
+        let vars = vars
+            .iter()
+            .map(|(name, value)| (name.clone(), value.to_f64().unwrap_or(f64::NAN)))
+            .collect();
+
+        Self {
+            vars,
+            functions,
+            builtins: meval::Context::new(),
+        }
+    }
+
+    fn var(&self, locals: &HashMap<String, f64>, name: &str) -> anyhow::Result<f64> {
+        locals
+            .get(name)
+            .or_else(|| self.vars.get(name))
+            .copied()
+            .or_else(|| self.builtins.get_var(name))
+            .ok_or_else(|| anyhow::anyhow!("unknown variable `{}`", name))
+    }
+
+    fn call(&self, name: &str, args: &[f64], depth: usize) -> anyhow::Result<f64> {
+        let Some(function) = self.functions.iter().find(|function| function.name == name) else {
+            return self
+                .builtins
+                .eval_func(name, args)
+                .map_err(|e| anyhow::anyhow!("function `{}`: {}", name, e));
+        };
+
+        if args.len() != function.args.len() {
+            anyhow::bail!("`{}` takes {} arguments", name, function.args.len());
+        }
+        if depth >= MAX_CALL_DEPTH {
+            anyhow::bail!("`{}` calls itself too many times", name);
+        }
+
+        let locals = function
+            .args
+            .iter()
+            .cloned()
+            .zip(args.iter().copied())
+            .collect();
+        let result = eval_rpn(&function.body, self, &locals, depth + 1);
+
+        // only the outermost call, or a recursive one would repeat it for every level
+        if depth == 0 {
+            result.map_err(|e| anyhow::anyhow!("in `{}`: {}", name, e))
+        } else {
+            result
+        }
+    }
+}
+
+// the same stack machine as meval's `eval_with_context`, except a zero divisor is an
+// error where it happens, instead of an inf that looks just like an overflow, and
+// user functions report their errors instead of returning NaN
+fn eval_rpn(
+    expr: &meval::Expr,
+    scope: &Scope,
+    locals: &HashMap<String, f64>,
+    depth: usize,
+) -> anyhow::Result<f64> {
+    let missing = || anyhow::anyhow!("unexpected end of expression");
+    let mut stack: Vec<f64> = Vec::new();
+
+    for token in expr.iter() {
+        let value = match token {
+            FloatToken::Number(n) => *n,
+            FloatToken::Var(name) => scope.var(locals, name)?,
+            FloatToken::Unary(op) => {
+                let x = stack.pop().ok_or_else(missing)?;
+                if *op == Operation::Minus {
+                    -x
+                } else {
+                    x
+                }
+            }
+            FloatToken::Binary(op) => {
+                let rhs = stack.pop().ok_or_else(missing)?;
+                let lhs = stack.pop().ok_or_else(missing)?;
+                match op {
+                    Operation::Plus => lhs + rhs,
+                    Operation::Minus => lhs - rhs,
+                    Operation::Times => lhs * rhs,
+                    Operation::Div | Operation::Rem if rhs == 0.0 => {
+                        anyhow::bail!("division by zero")
+                    }
+                    Operation::Pow if lhs == 0.0 && rhs < 0.0 => {
+                        anyhow::bail!("division by zero")
+                    }
+                    Operation::Div => lhs / rhs,
+                    Operation::Rem => lhs % rhs,
+                    Operation::Pow => lhs.powf(rhs),
+                }
+            }
+            FloatToken::Func(name, Some(count)) if *count <= stack.len() => {
+                let args = stack.split_off(stack.len() - count);
+                scope.call(name, &args, depth)?
+            }
+            _ => return Err(missing()),
+        };
+        stack.push(value);
+    }
+
+    match (stack.pop(), stack.is_empty()) {
+        (Some(n), true) => Ok(n),
+        _ => Err(missing()),
+    }
+}
+
+pub fn eval_float(input: &str, scope: &Scope) -> anyhow::Result<f64> {
+    check_parens(input)?;
+
+    // meval reports byte offsets into the trimmed string
+    let offset = input.len() - input.trim_start().len();
+    let column = |byte: usize| input[..(offset + byte).min(input.len())].chars().count() + 1;
+
+    let expr = input.trim().parse::<meval::Expr>().map_err(|e| match e {
+        meval::Error::ParseError(meval::ParseError::UnexpectedToken(byte)) => {
+            anyhow::anyhow!("unexpected token at column {}", column(byte))
+        }
+        meval::Error::ParseError(meval::ParseError::MissingArgument) => {
+            anyhow::anyhow!("unexpected end of expression")
+        }
+        e => anyhow::anyhow!("{}", e),
+    })?;
+    let n = eval_rpn(&expr, scope, &HashMap::new(), 0)?;
+
+    if n.is_nan() {
+        anyhow::bail!("result is undefined");
+    } else if n.is_infinite() {
+        anyhow::bail!("overflow");
+    }
+
+    Ok(n)
+}
+
+/// How results are turned into text, from the `[calculator]` config section.
+#[derive(Clone)]
+pub struct Format {
+    pub precision: usize,
+    pub rounding: Rounding,
+    pub digit_grouping: bool,
+    pub scientific_above: usize,
+    pub scientific_below: usize,
+}
+
+impl Format {
+    pub fn new(config: &config::Calculator) -> Self {
+        Self {
+            precision: config.precision,
+            rounding: config.rounding,
+            digit_grouping: config.digit_grouping,
+            scientific_above: config.scientific_above,
+            scientific_below: config.scientific_below,
+        }
+    }
+}
+
+fn pow10(exp: usize) -> BigRational {
+    BigRational::from_integer(num::pow(BigInt::from(10), exp))
+}
+
+fn has_few_digits(value: &BigRational) -> bool {
+    value.numer().bits().max(value.denom().bits()) <= MAX_DIGITS_BITS
+}
+
+fn round(value: &BigRational, mode: Rounding) -> BigInt {
+    let trunc = value.trunc().to_integer();
+    let frac = (value - value.trunc()).abs();
+    if frac.is_zero() {
+        return trunc;
+    }
+
+    let half = BigRational::new(BigInt::one(), BigInt::from(2));
+    let away = match mode {
+        Rounding::Down => false,
+        Rounding::Up => true,
+        Rounding::Floor => value.is_negative(),
+        Rounding::Ceiling => value.is_positive(),
+        Rounding::HalfUp => frac >= half,
+        Rounding::HalfEven => frac > half || (frac == half && trunc.is_odd()),
+    };
+
+    if !away {
+        trunc
+    } else if value.is_negative() {
+        trunc - 1
+    } else {
+        trunc + 1
+    }
+}
+
+fn group_digits(digits: &str) -> String {
+    let mut grouped = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(c);
+    }
+
+    grouped
+}
+
+// rounds to `places` decimals and renders without trailing zeros
+fn format_fixed(value: &BigRational, places: usize, rounding: Rounding, group: bool) -> String {
+    let scaled = round(&(value * pow10(places)), rounding);
+    let digits = format!("{:0>width$}", scaled.abs(), width = places + 1);
+
+    let (int_part, frac_part) = digits.split_at(digits.len() - places);
+    let int_part = if group {
+        group_digits(int_part)
+    } else {
+        int_part.to_string()
+    };
+    let frac_part = frac_part.trim_end_matches('0');
+
+    let sign = if scaled.is_negative() { "-" } else { "" };
+    if frac_part.is_empty() {
+        format!("{}{}", sign, int_part)
+    } else {
+        format!("{}{}.{}", sign, int_part, frac_part)
+    }
+}
+
+fn format_scientific(value: &BigRational, format: &Format) -> String {
+    let mut numer = value.numer().abs();
+    let mut denom = value.denom().clone();
+
+    // the bit lengths get the exponent to within one, counting digits one at a time
+    // would take forever on something like 1/2^500000
+    let bits = numer.bits() as f64 - denom.bits() as f64;
+    let mut exp = (bits * std::f64::consts::LOG10_2).floor() as i64;
+    if exp >= 0 {
+        denom *= num::pow(BigInt::from(10), exp as usize);
+    } else {
+        numer *= num::pow(BigInt::from(10), exp.unsigned_abs() as usize);
+    }
+
+    // so that 1 <= numer / denom < 10
+    while numer < denom {
+        numer *= 10u32;
+        exp -= 1;
+    }
+    while numer >= &denom * 10u32 {
+        denom *= 10u32;
+        exp += 1;
+    }
+
+    // a couple more places than are shown, and a last digit that's only zero if
+    // everything after it is too, so it rounds the same as the exact value. keeps
+    // the gcds in the rational maths small
+    let places = format.precision + 2;
+    let (digits, rest) = (numer * num::pow(BigInt::from(10), places)).div_rem(&denom);
+    let digits = digits * 10u32 + u32::from(!rest.is_zero());
+    let mut mantissa = BigRational::new(digits, num::pow(BigInt::from(10), places + 1));
+    if value.is_negative() {
+        mantissa = -mantissa;
+    }
+
+    // rounding can carry into another digit (9.99 -> 10)
+    let mut text = format_fixed(&mantissa, format.precision, format.rounding, false);
+    if text.trim_start_matches('-').starts_with("10") {
+        text = format_fixed(
+            &(mantissa / pow10(1)),
+            format.precision,
+            format.rounding,
+            false,
+        );
+        exp += 1;
+    }
+
+    format!("{}e{}", text, exp)
+}
+
+fn is_scientific(value: &BigRational, format: &Format) -> bool {
+    let abs = value.abs();
+
+    !abs.is_zero()
+        && (abs >= pow10(format.scientific_above)
+            || abs < BigRational::one() / pow10(format.scientific_below))
+}
+
+#[derive(Clone)]
+pub struct Output {
+    pub display: String,
+    pub value: String,
+}
+
+impl Output {
+    fn plain(text: String) -> Self {
+        Self {
+            display: text.clone(),
+            value: text,
+        }
+    }
+}
+
+/// Renders a number for display, with the ungrouped text for the clipboard.
+pub fn format_number(value: &BigRational, format: &Format) -> Vec<Output> {
+    if is_scientific(value, format) {
+        let mut outputs = vec![Output::plain(format_scientific(value, format))];
+
+        // the whole point of exact integers is being able to see every digit
+        if value.is_integer() && has_few_digits(value) {
+            outputs.push(Output {
+                display: format_fixed(value, 0, format.rounding, format.digit_grouping),
+                value: value.to_integer().to_string(),
+            });
+        }
+
+        return outputs;
+    }
+
+    let mut outputs = vec![Output {
+        display: format_fixed(
+            value,
+            format.precision,
+            format.rounding,
+            format.digit_grouping,
+        ),
+        value: format_fixed(value, format.precision, format.rounding, false),
+    }];
+
+    // show the exact fraction when the decimal had to be rounded
+    if !(value * pow10(format.precision)).is_integer() && has_few_digits(value) {
+        outputs.push(Output::plain(value.to_string()));
+    }
+
+    outputs
+}
+
+fn with_radix(n: &BigInt, prefix: &str, radix: u32) -> String {
+    let digits = n.abs().to_str_radix(radix);
+    if n.is_negative() {
+        format!("-{}{}", prefix, digits)
+    } else {
+        format!("{}{}", prefix, digits)
+    }
+}
+
+/// Formats an integer in decimal, hex, binary and octal, in that order. Negative
+/// numbers keep their sign (`-0x1`) rather than being shown as two's complement,
+/// which would need a word size.
+pub fn int_formats(n: &BigInt, format: &Format) -> Vec<Output> {
+    let decimal = Output {
+        display: format_fixed(
+            &BigRational::from_integer(n.clone()),
+            0,
+            format.rounding,
+            format.digit_grouping,
+        ),
+        value: n.to_string(),
+    };
+
+    vec![
+        decimal,
+        Output::plain(with_radix(n, "0x", 16)),
+        Output::plain(with_radix(n, "0b", 2)),
+        Output::plain(with_radix(n, "0o", 8)),
+    ]
+}
+
+pub struct Function {
+    name: String,
+    args: Vec<String>,
+    body: meval::Expr,
+}
+
+/// Parses a user function definition like `hyp(a, b) = sqrt(a^2 + b^2)`.
+pub fn parse_function(def: &str) -> anyhow::Result<Function> {
+    let (head, body) = def
+        .split_once('=')
+        .ok_or_else(|| anyhow::anyhow!("missing `=` in `{}`", def))?;
+    let (name, args) = head
+        .trim()
+        .strip_suffix(')')
+        .and_then(|head| head.split_once('('))
+        .ok_or_else(|| anyhow::anyhow!("expected `name(args) = body` in `{}`", def))?;
+
+    let args: Vec<String> = args
+        .split(',')
+        .map(|arg| arg.trim().to_string())
+        .filter(|arg| !arg.is_empty())
+        .collect();
+    let body = body
+        .trim()
+        .parse::<meval::Expr>()
+        .map_err(|e| anyhow::anyhow!("couldn't parse `{}`: {}", def, e))?;
+
+    Ok(Function {
+        name: name.trim().to_string(),
+        args,
+        body,
+    })
+}
+
+pub struct HistoryEntry {
+    pub input: String,
+    pub output: Output,
+}
+
+#[derive(Clone)]
+pub struct Evaluation {
+    pub input: String,
+    pub name: Option<String>,
+    pub value: BigRational,
+    pub outputs: Vec<Output>,
+}
+
+/// Calculator state that outlives a single query: `ans`, user variables and
+/// the scrollback of committed calculations.
+pub struct Session {
+    vars: HashMap<String, BigRational>,
+    functions: Vec<Function>,
+    rates: Option<Rates>,
+    format: Format,
+    pub history: Vec<HistoryEntry>,
+    // the search runs every frame, and a huge power takes a few ms to work out
+    last_eval: RefCell<Option<(String, Result<Evaluation, String>)>>,
+}
+
+// splits `x = 3` into the variable name and the input with the name blanked
+// out, so error columns still line up with what the user typed
+fn split_assignment(input: &str) -> (Option<String>, String) {
+    if let Some((name, expr)) = input.split_once('=') {
+        let name = name.trim();
+        let is_ident = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+
+        if is_ident && name != "ans" {
+            let blank = " ".repeat(input[..input.len() - expr.len()].chars().count());
+            return (Some(name.to_string()), format!("{}{}", blank, expr));
+        }
+    }
+
+    (None, input.to_string())
+}
+
+impl Session {
+    pub fn new(config: &config::Calculator) -> Self {
+        let functions = config
+            .functions
+            .iter()
+            .filter_map(|def| match parse_function(def) {
+                Ok(function) => Some(function),
+                Err(e) => {
+                    println!("skipping calculator function: {}", e);
+                    None
+                }
+            })
+            .collect();
+
+        let rates = config.rates_path.as_ref().and_then(|path| {
+            let path = shellexpand::env(path).expect("couldn't get rates path");
+            match Rates::load(Path::new(path.as_ref())) {
+                Ok(rates) => Some(rates),
+                Err(e) => {
+                    println!("couldn't load exchange rates: {}", e);
+                    None
+                }
+            }
+        });
+
+        Self {
+            vars: HashMap::new(),
+            functions,
+            rates,
+            format: Format::new(config),
+            history: Vec::new(),
+            last_eval: RefCell::new(None),
+        }
+    }
+
+    // returns the value and whether it was computed exactly
+    fn value(&self, expr: &str) -> anyhow::Result<(BigRational, bool)> {
+        match eval_exact(expr, &self.vars) {
+            Ok(value) => Ok((value, true)),
+            // programmer syntax means meval won't understand it either
+            Err(e) if e.is::<Inexact>() && !is_programmer_expr(expr) => {
+                let n = eval_float(expr, &Scope::new(&self.vars, &self.functions))?;
+                let value = BigRational::from_float(n)
+                    .ok_or_else(|| anyhow::anyhow!("result is undefined"))?;
+                Ok((value, false))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn eval(&self, input: &str) -> anyhow::Result<Evaluation> {
+        if let Some((last_input, result)) = &*self.last_eval.borrow() {
+            if last_input == input {
+                return result.clone().map_err(anyhow::Error::msg);
+            }
+        }
+
+        let result = self.eval_uncached(input);
+        let cached = match &result {
+            Ok(evaluation) => Ok(evaluation.clone()),
+            Err(e) => Err(e.to_string()),
+        };
+        *self.last_eval.borrow_mut() = Some((input.to_string(), cached));
+        result
+    }
+
+    fn eval_uncached(&self, input: &str) -> anyhow::Result<Evaluation> {
+        let (name, expr) = split_assignment(input);
+
+        if let Some(rates) = &self.rates {
+            if let Some((amount, from, to)) = rates.parse_query(&expr) {
+                return self.convert(input, name, rates, amount, &from, &to);
+            }
+        }
+
+        let (value, exact) = self.value(&expr)?;
+        let mut format = self.format.clone();
+        if !exact {
+            // floats are only good for ~15 digits, don't pretend otherwise
+            format.precision = format.precision.min(15);
+        }
+
+        let mut outputs =
+            if is_programmer_expr(&expr) && value.is_integer() && has_few_digits(&value) {
+                int_formats(&value.to_integer(), &format)
+            } else {
+                format_number(&value, &format)
+            };
+
+        // the "exact" alternatives of a float are just noise
+        if !exact {
+            outputs.truncate(1);
+        }
+
+        Ok(Evaluation {
+            input: input.trim().to_string(),
+            name,
+            value,
+            outputs,
+        })
+    }
+
+    fn convert(
+        &self,
+        input: &str,
+        name: Option<String>,
+        rates: &Rates,
+        amount: &str,
+        from: &str,
+        to: &str,
+    ) -> anyhow::Result<Evaluation> {
+        let (amount, _) = self.value(amount)?;
+        let rate = rates
+            .rate(from, to)
+            .ok_or_else(|| anyhow::anyhow!("no rate for {} to {}", from, to))?;
+        let value = amount * &rate;
+
+        let mut format = self.format.clone();
+        format.precision = format.precision.min(2);
+        let converted = format_number(&value, &format).remove(0);
+
+        format.precision = self.format.precision.min(6);
+        let rate_text = format_number(&rate, &format).remove(0);
+        let as_of = match &rates.date {
+            Some(date) => format!(", as of {}", date),
+            None => String::new(),
+        };
+
+        let outputs = vec![
+            Output {
+                display: format!("{} {}", converted.display, to),
+                value: converted.value,
+            },
+            Output {
+                display: format!("1 {} = {} {}{}", from, rate_text.display, to, as_of),
+                value: rate_text.value,
+            },
+        ];
+
+        Ok(Evaluation {
+            input: input.trim().to_string(),
+            name,
+            value,
+            outputs,
+        })
+    }
+
+    pub fn commit(&mut self, evaluation: Evaluation) {
+        // `ans` and variables are about to change
+        self.last_eval.replace(None);
+
+        if let Some(name) = &evaluation.name {
+            self.vars.insert(name.clone(), evaluation.value.clone());
+        }
+        self.vars.insert("ans".to_string(), evaluation.value);
+
+        self.history.push(HistoryEntry {
+            input: evaluation.input,
+            output: evaluation.outputs[0].clone(),
+        });
+    }
+
+    pub fn has_var(&self, name: &str) -> bool {
+        self.vars.contains_key(name)
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+
+    /// Other ways to write a value, for the preview panel: scientific notation, and the
+    /// exact fraction or the integer in every base.
+    pub fn alternatives(&self, value: &BigRational) -> Vec<Output> {
+        let mut outputs = Vec::new();
+        if !value.is_zero() {
+            outputs.push(Output::plain(format_scientific(value, &self.format)));
+        }
+
+        if !has_few_digits(value) {
+            return outputs;
+        }
+        if value.is_integer() {
+            outputs.extend(int_formats(&value.to_integer(), &self.format));
+        } else {
+            outputs.push(Output::plain(value.to_string()));
+        }
+
+        outputs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(n: i64) -> BigRational {
+        BigRational::from_integer(BigInt::from(n))
+    }
+
+    fn eval(input: &str) -> BigRational {
+        eval_exact(input, &HashMap::new()).unwrap()
+    }
+
+    #[test]
+    fn radix_literals() {
+        assert_eq!(parse_literal("0xff").unwrap(), int(255));
+        assert_eq!(parse_literal("0XFF").unwrap(), int(255));
+        assert_eq!(parse_literal("0b1010").unwrap(), int(10));
+        assert_eq!(parse_literal("0o17").unwrap(), int(15));
+        assert_eq!(parse_literal("0xdead_beef").unwrap(), int(0xdead_beef));
+        assert!(parse_literal("0x").is_err());
+        assert!(parse_literal("0b102").is_err());
+        assert!(parse_literal("0o8").is_err());
+    }
+
+    #[test]
+    fn decimal_literals() {
+        assert_eq!(parse_literal("1_000").unwrap(), int(1000));
+        assert_eq!(
+            parse_literal(".5").unwrap(),
+            BigRational::new(BigInt::from(1), BigInt::from(2))
+        );
+        assert_eq!(
+            parse_literal("1.25e-3").unwrap(),
+            BigRational::new(BigInt::from(1), BigInt::from(800))
+        );
+        assert_eq!(parse_literal("2.5E2").unwrap(), int(250));
+        assert!(parse_literal("1e").is_err());
+        assert!(parse_literal("1e99999").is_err());
+    }
+
+    #[test]
+    fn literals_in_expressions() {
+        assert_eq!(eval("0xff + 0b1 + 0o10"), int(264));
+        assert_eq!(eval("1e3 - 1"), int(999));
+        assert!(eval_exact("0xfg", &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3"), int(7));
+        assert_eq!(eval("(1 + 2) * 3"), int(9));
+        assert_eq!(eval("7 - 2 - 1"), int(4));
+        assert_eq!(eval("1 << 2 + 1"), int(8));
+        assert_eq!(eval("6 & 3 | 8"), int(10));
+        assert_eq!(eval("1 | 6 xor 3 & 5"), int(7));
+        assert_eq!(eval("-2^2"), int(-4));
+        assert_eq!(
+            eval("2^-1"),
+            BigRational::new(BigInt::from(1), BigInt::from(2))
+        );
+        assert_eq!(eval("2^3^2"), int(512));
+        assert_eq!(eval("2 * 3!"), int(12));
+        assert_eq!(eval("~0 & 0xf"), int(15));
+    }
+
+    #[test]
+    fn caret_is_xor_with_radix_literals() {
+        assert_eq!(eval("2 ^ 3"), int(8));
+        assert_eq!(eval("0xf0 ^ 0xff"), int(0x0f));
+        assert_eq!(eval("0b11 ** 2"), int(9));
+        // between | and &, same as `xor`
+        assert_eq!(eval("1 | 0x6 ^ 3 & 5"), int(7));
+    }
+
+    #[test]
+    fn float_errors() {
+        let scope = Scope::new(&HashMap::new(), &[]);
+        let error = |input: &str| eval_float(input, &scope).unwrap_err().to_string();
+
+        assert_eq!(error("1/2 + 10^400"), "overflow");
+        assert_eq!(error("exp(1000) / 2"), "overflow");
+        assert_eq!(error("1 / sin(0)"), "division by zero");
+        assert_eq!(error("5 % (pi - pi)"), "division by zero");
+        assert_eq!(error("0^-1"), "division by zero");
+        assert_eq!(error("sqrt(-1)"), "result is undefined");
+        assert_eq!(error("foo + 1"), "unknown variable `foo`");
+        assert_eq!(error("(1 + 2"), "unbalanced parenthesis at column 1");
+        assert_eq!(eval_float("1 / 4 + sin(0)", &scope).unwrap(), 0.25);
+    }
+
+    #[test]
+    fn user_functions() {
+        let functions: Vec<Function> = [
+            "hyp(a, b) = sqrt(a^2 + b^2)",
+            "circle(r) = pi * r^2",
+            "scaled(x) = x * k",
+            "twice(x) = hyp(x, 0) * 2",
+            "broken(x) = x + nope",
+            "forever(x) = forever(x)",
+        ]
+        .iter()
+        .map(|def| parse_function(def).unwrap())
+        .collect();
+        let vars = HashMap::from([("k".to_string(), int(10))]);
+        let scope = Scope::new(&vars, &functions);
+        let eval = |input: &str| eval_float(input, &scope);
+
+        assert_eq!(eval("hyp(3, 4)").unwrap(), 5.0);
+        assert_eq!(eval("circle(1)").unwrap(), std::f64::consts::PI);
+        assert_eq!(eval("scaled(2)").unwrap(), 20.0);
+        assert_eq!(eval("twice(3)").unwrap(), 6.0);
+        assert_eq!(
+            eval("broken(1)").unwrap_err().to_string(),
+            "in `broken`: unknown variable `nope`"
+        );
+        assert_eq!(
+            eval("forever(1)").unwrap_err().to_string(),
+            "in `forever`: `forever` calls itself too many times"
+        );
+        assert_eq!(
+            eval("hyp(1)").unwrap_err().to_string(),
+            "`hyp` takes 2 arguments"
+        );
+    }
+
+    fn format(precision: usize) -> Format {
+        Format {
+            precision,
+            rounding: Rounding::HalfEven,
+            digit_grouping: false,
+            scientific_above: 20,
+            scientific_below: 10,
+        }
+    }
+
+    #[test]
+    fn scientific_notation() {
+        let sci = |value: BigRational| format_scientific(&value, &format(3));
+
+        assert_eq!(sci(int(1)), "1e0");
+        assert_eq!(sci(int(123_456)), "1.235e5");
+        assert_eq!(sci(int(-123_456)), "-1.235e5");
+        assert_eq!(sci(int(123) / int(1_000_000)), "1.23e-4");
+        assert_eq!(sci(int(-1) / int(3)), "-3.333e-1");
+        // 9.9995 rounds up to 10.000
+        assert_eq!(sci(int(999_950)), "1e6");
+        assert_eq!(sci(int(1) / int(7) / pow10(30)), "1.429e-31");
+    }
+
+    #[test]
+    fn huge_numbers() {
+        let session = Session::new(&config::Calculator::default());
+        let outputs = |input: &str| -> Vec<String> {
+            let evaluation = session.eval(input).unwrap();
+            evaluation.outputs.into_iter().map(|o| o.value).collect()
+        };
+
+        // only scientific notation, not 150000 digits
+        assert_eq!(outputs("2^500000"), ["9.95020413323058325018e150514"]);
+        assert_eq!(outputs("1/2^500000"), ["1.00500450705359039435e-150515"]);
+        assert_eq!(outputs("10^-260000"), ["1e-260000"]);
+        assert_eq!(outputs("0x1 << 100000").len(), 1);
+        assert_eq!(outputs("2^100").len(), 2);
+    }
+
+    #[test]
+    fn negative_radix_output() {
+        let outputs: Vec<String> = int_formats(&BigInt::from(-10), &format(10))
+            .into_iter()
+            .map(|output| output.value)
+            .collect();
+        assert_eq!(outputs, ["-10", "-0xa", "-0b1010", "-0o12"]);
+    }
+}
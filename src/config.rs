@@ -7,29 +7,171 @@ use figment::{
 use serde::{Deserialize, Serialize};
 use std::fs::{self, File};
 
+// logical pixels like 640, or a percentage of the monitor's work area like "40%"
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum Length {
+    Pixels(u32),
+    Percent(String),
+}
+
+impl Length {
+    /// In logical pixels, `of` being what a percentage is a percentage of.
+    pub fn resolve(&self, of: f32) -> anyhow::Result<f32> {
+        match self {
+            Length::Pixels(pixels) => Ok(*pixels as f32),
+            Length::Percent(percent) => {
+                let number = percent
+                    .trim()
+                    .strip_suffix('%')
+                    .ok_or_else(|| anyhow::anyhow!("`{}` isn't a percentage", percent))?;
+                Ok(number.trim().parse::<f32>()? / 100.0 * of)
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Position {
+    // at `x` and `y`
+    Absolute,
+    Center,
+    // centered on the line a third of the way down, like a spotlight
+    TopThird,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Monitor {
+    Cursor,
+    // the one with the focused window, only on windows
+    Focus,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Window {
-    pub width: u32,
-    pub height: u32,
+    pub width: Length,
+    // the most it grows to with auto_height
+    pub height: Length,
+    // in physical pixels, only used with position = "absolute"
     pub x: u32,
     pub y: u32,
+    pub position: Position,
+    // which monitor the position and percentages go by
+    pub monitor: Monitor,
+    // shrink to fit the results, up to `height`
+    pub auto_height: bool,
 }
 
 impl Default for Window {
     fn default() -> Self {
         Self {
-            width: 640,
-            height: 320,
+            width: Length::Pixels(640),
+            height: Length::Pixels(320),
             x: 640,
             y: 380,
+            position: Position::Center,
+            monitor: Monitor::Cursor,
+            auto_height: true,
+        }
+    }
+}
+
+// a shortcut_paths entry, either just the path or a table like
+// { path = "...", max_depth = 2, prefix = "steam: ", exclude = ["Uninstall *.lnk"] }
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum ShortcutPath {
+    Path(String),
+    Root(ShortcutRoot),
+}
+
+// anything left out of a table gets the default for the platform
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ShortcutRoot {
+    pub path: String,
+    // no limit when unset
+    pub max_depth: Option<usize>,
+    pub extensions: Vec<String>,
+    pub follow_symlinks: bool,
+    // dotfiles, and on windows anything with the hidden attribute. on by default on
+    // windows and off on macos, like before tables were added
+    pub include_hidden: bool,
+    // put in front of the names of everything under this path, e.g. "steam: "
+    pub prefix: Option<String>,
+    // patterns like in ignore_paths that only apply under this path. when include
+    // isn't empty, only shortcuts matching one of its patterns are listed
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
+impl Default for ShortcutRoot {
+    #[cfg(target_os = "windows")]
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            extensions: vec!["lnk".to_string(), "url".to_string()],
+            include_hidden: true,
+            ..Self::common()
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    fn default() -> Self {
+        Self {
+            // arbitrary limit to prevent long load times with apps that store stuff incorrectly
+            // (looking at you unity)
+            max_depth: Some(5),
+            extensions: vec!["app".to_string()],
+            ..Self::common()
+        }
+    }
+}
+
+impl ShortcutRoot {
+    fn common() -> Self {
+        Self {
+            path: String::new(),
+            max_depth: None,
+            extensions: vec![],
+            follow_symlinks: false,
+            include_hidden: false,
+            prefix: None,
+            include: vec![],
+            exclude: vec![],
+        }
+    }
+}
+
+impl ShortcutPath {
+    pub fn root(&self) -> ShortcutRoot {
+        match self {
+            ShortcutPath::Path(path) => ShortcutRoot {
+                path: path.clone(),
+                ..ShortcutRoot::default()
+            },
+            ShortcutPath::Root(root) => root.clone(),
         }
     }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Search {
-    pub shortcut_paths: Vec<String>,
+    pub shortcut_paths: Vec<ShortcutPath>,
+    // see ignore.rs for the pattern syntax
     pub ignore_paths: Vec<String>,
+    // show a calculator result for queries like `12*7` without the `=` prefix
+    pub inline_calculator: bool,
+    // their own "open with" entries in the actions menu, e.g. "code" or "notepad". any
+    // indexed app can be picked from "open with..."
+    pub open_with: Vec<String>,
+    // in path mode (`~/Doc`), toggled with ctrl+h
+    pub show_hidden_files: bool,
+    // list every copy of an app that's in more than one of shortcut_paths, with where
+    // it's from, instead of only the one from the user's own folders
+    pub show_duplicates: bool,
     pub aliases: Map<String, String>,
 }
 
@@ -37,13 +179,20 @@ impl Default for Search {
     #[cfg(target_os = "windows")]
     fn default() -> Self {
         Self {
-            shortcut_paths: vec![
-                "${AppData}\\Microsoft\\Windows\\Start Menu".to_string(),
-                "${ProgramData}\\Microsoft\\Windows\\Start Menu".to_string(),
-            ],
+            shortcut_paths: [
+                "${AppData}\\Microsoft\\Windows\\Start Menu",
+                "${ProgramData}\\Microsoft\\Windows\\Start Menu",
+            ]
+            .iter()
+            .map(|path| ShortcutPath::Path(path.to_string()))
+            .collect(),
             ignore_paths: vec![
                 "${AppData}\\Microsoft\\Windows\\Start Menu\\Programs\\Startup".to_string(),
             ],
+            inline_calculator: true,
+            open_with: vec![],
+            show_hidden_files: false,
+            show_duplicates: false,
             aliases: Map::new(),
         }
     }
@@ -51,12 +200,19 @@ impl Default for Search {
     #[cfg(target_os = "macos")]
     fn default() -> Self {
         Self {
-            shortcut_paths: vec![
-                "/Applications".to_string(),
-                "/System/Applications".to_string(),
-                "${HOME}/Applications".to_string(),
-            ],
+            shortcut_paths: [
+                "/Applications",
+                "/System/Applications",
+                "${HOME}/Applications",
+            ]
+            .iter()
+            .map(|path| ShortcutPath::Path(path.to_string()))
+            .collect(),
             ignore_paths: vec![],
+            inline_calculator: true,
+            open_with: vec![],
+            show_hidden_files: false,
+            show_duplicates: false,
             aliases: Map::new(),
         }
     }
@@ -65,12 +221,208 @@ impl Default for Search {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct General {
     pub hotkey: Vec<String>,
+    // command the path is appended to for "run as administrator", e.g. ["pkexec"] or
+    // ["sudo"] (run with -A, so SUDO_ASKPASS has to be set). windows always uses the UAC
+    // prompt, and macos the system password prompt when this is empty
+    pub elevate_with: Vec<String>,
 }
 
 impl Default for General {
     fn default() -> Self {
         Self {
             hotkey: vec!["LAlt".to_string(), "Backspace".to_string()],
+            elevate_with: vec![],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "kebab-case")]
+pub enum Rounding {
+    HalfUp,
+    HalfEven,
+    Down,
+    Up,
+    Floor,
+    Ceiling,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Calculator {
+    // e.g. "hyp(a, b) = sqrt(a^2 + b^2)"
+    pub functions: Vec<String>,
+
+    // max digits after the decimal point
+    pub precision: usize,
+    pub rounding: Rounding,
+    pub digit_grouping: bool,
+    // switch to scientific notation at >= 10^above or < 10^-below
+    pub scientific_above: usize,
+    pub scientific_below: usize,
+
+    // json or csv exchange rates for `=100 usd in eur`
+    pub rates_path: Option<String>,
+}
+
+impl Default for Calculator {
+    fn default() -> Self {
+        Self {
+            functions: vec![],
+            precision: 20,
+            rounding: Rounding::HalfEven,
+            digit_grouping: false,
+            scientific_above: 21,
+            scientific_below: 7,
+            rates_path: None,
+        }
+    }
+}
+
+// `f ` mode. include/exclude are globs matched against the path under its root,
+// e.g. "**/*.pdf" or "**/node_modules", case-insensitively and with `*` stopping at a
+// `/` like in ignore_paths
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Files {
+    pub roots: Vec<String>,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    // e.g. ["pdf", "docx"], empty means any
+    pub extensions: Vec<String>,
+    pub max_depth: usize,
+    // 0 only indexes once at startup
+    pub refresh_minutes: u64,
+}
+
+impl Default for Files {
+    #[cfg(target_os = "windows")]
+    fn default() -> Self {
+        Self {
+            roots: vec![
+                "${USERPROFILE}\\Documents".to_string(),
+                "${USERPROFILE}\\Desktop".to_string(),
+            ],
+            ..Self::common()
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    fn default() -> Self {
+        Self {
+            roots: vec![
+                "${HOME}/Documents".to_string(),
+                "${HOME}/Desktop".to_string(),
+            ],
+            ..Self::common()
+        }
+    }
+}
+
+impl Files {
+    fn common() -> Self {
+        Self {
+            roots: vec![],
+            include: vec![],
+            exclude: vec![
+                "**/.*".to_string(),
+                "**/node_modules".to_string(),
+                "**/target".to_string(),
+            ],
+            extensions: vec![],
+            max_depth: 8,
+            refresh_minutes: 30,
+        }
+    }
+}
+
+// `>` mode. the typed command is appended as the last argument of `command` and `terminal`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Shell {
+    pub command: Vec<String>,
+    pub terminal: Vec<String>,
+    // output lines shown under the command
+    pub max_lines: usize,
+}
+
+impl Default for Shell {
+    #[cfg(target_os = "windows")]
+    fn default() -> Self {
+        Self {
+            command: vec!["cmd".to_string(), "/C".to_string()],
+            terminal: ["cmd", "/C", "start", "cmd", "/K"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            max_lines: 20,
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    fn default() -> Self {
+        Self {
+            command: vec!["/bin/zsh".to_string(), "-c".to_string()],
+            terminal: [
+                "osascript",
+                "-e",
+                "on run argv",
+                "-e",
+                "tell application \"Terminal\" to do script (item 1 of argv)",
+                "-e",
+                "tell application \"Terminal\" to activate",
+                "-e",
+                "end run",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+            max_lines: 20,
+        }
+    }
+}
+
+// each command takes a list of chords like "ctrl+shift+enter", see keys.rs for key names
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct Keys {
+    pub next: Vec<String>,
+    // fills in the selected path in path mode, otherwise the same as next
+    pub complete: Vec<String>,
+    pub previous: Vec<String>,
+    pub page_down: Vec<String>,
+    pub page_up: Vec<String>,
+    pub first: Vec<String>,
+    pub last: Vec<String>,
+    pub run: Vec<String>,
+    pub run_secondary: Vec<String>,
+    pub run_elevated: Vec<String>,
+    pub copy_path: Vec<String>,
+    pub open_folder: Vec<String>,
+    pub close: Vec<String>,
+    pub clear_query: Vec<String>,
+    pub history_up: Vec<String>,
+    pub toggle_hidden: Vec<String>,
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        let keys = |keys: &[&str]| keys.iter().map(|key| key.to_string()).collect();
+
+        Self {
+            next: keys(&["down", "ctrl+n"]),
+            complete: keys(&["tab"]),
+            previous: keys(&["up", "shift+tab", "ctrl+p"]),
+            page_down: keys(&["pagedown"]),
+            page_up: keys(&["pageup"]),
+            first: keys(&["home"]),
+            last: keys(&["end"]),
+            run: keys(&["enter"]),
+            run_secondary: keys(&["ctrl+enter"]),
+            run_elevated: keys(&["ctrl+shift+enter"]),
+            copy_path: keys(&["ctrl+shift+c"]),
+            open_folder: keys(&["ctrl+o"]),
+            close: keys(&["escape"]),
+            clear_query: keys(&["ctrl+u"]),
+            history_up: keys(&["ctrl+up"]),
+            toggle_hidden: keys(&["ctrl+h"]),
         }
     }
 }
@@ -78,6 +430,10 @@ impl Default for General {
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Style {
     pub font: Option<String>,
+    // one line per result, with the subtitle (path, description...) only on hover
+    pub compact: bool,
+    // a panel next to the results with more about the selected one
+    pub preview: bool,
 
     pub bg_color: Option<String>,
     pub input_bg_color: Option<String>,
@@ -86,6 +442,8 @@ pub struct Style {
 
     pub text_color: Option<String>,
     pub stroke_color: Option<String>,
+    // the characters of a result that match the query
+    pub highlight_color: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
@@ -93,6 +451,10 @@ pub struct Config {
     pub window: Window,
     pub search: Search,
     pub general: General,
+    pub calculator: Calculator,
+    pub files: Files,
+    pub shell: Shell,
+    pub keys: Keys,
     pub style: Style,
 }
 
@@ -110,17 +472,43 @@ pub fn get_config() -> Config {
     }
 
     // read config
-    let config: Config = Figment::from(Serialized::defaults(Config::default()))
+    let mut config: Config = Figment::from(Serialized::defaults(Config::default()))
         .merge(Toml::file(&config_path))
         .extract()
         .expect("couldn't load config");
 
-    // write config
-    fs::write(
-        &config_path,
-        toml::to_string(&config).expect("couldn't save config"),
-    )
-    .expect("couldn't save config");
+    if let Err(e) = crate::keys::Bindings::new(&config.keys) {
+        panic!("invalid key binding in config: {}", e);
+    }
+    if let Err(e) = crate::files::Filter::new(&config.files) {
+        panic!("invalid pattern in [files]: {}", e);
+    }
+    if let Err(e) = crate::ignore::RootFilter::all(&config.search) {
+        panic!("invalid pattern in [search]: {}", e);
+    }
+    for length in [&config.window.width, &config.window.height] {
+        if let Err(e) = length.resolve(0.0) {
+            panic!("invalid size in [window]: {}", e);
+        }
+    }
+
+    // toml can't have plain strings and tables in the same array
+    let shortcut_paths = &mut config.search.shortcut_paths;
+    if shortcut_paths
+        .iter()
+        .any(|path| matches!(path, ShortcutPath::Root(_)))
+    {
+        for path in shortcut_paths.iter_mut() {
+            *path = ShortcutPath::Root(path.root());
+        }
+    }
+
+    // write config. going through a Value moves the shortcut_paths tables after the
+    // plain values in [search], which toml requires
+    let contents = toml::Value::try_from(&config)
+        .map(|value| value.to_string())
+        .expect("couldn't save config");
+    fs::write(&config_path, contents).expect("couldn't save config");
 
     config
 }
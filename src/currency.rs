@@ -0,0 +1,158 @@
+// offline currency conversion for calculator mode (`=100 usd in eur`)
+// rates come from a user supplied json or csv file, there's no network at runtime
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use num::{BigRational, Zero};
+use serde::Deserialize;
+
+use crate::calc;
+
+// { "base": "EUR", "date": "2026-10-01", "rates": { "USD": 1.08, "GBP": "0.86" } }
+#[derive(Deserialize)]
+struct RatesFile {
+    base: Option<String>,
+    date: Option<String>,
+    rates: HashMap<String, serde_json::Value>,
+}
+
+pub struct Rates {
+    pub date: Option<String>,
+    // units of each currency per one unit of the (implicit) base
+    rates: HashMap<String, BigRational>,
+}
+
+fn parse_rate(code: &str, rate: &str) -> anyhow::Result<BigRational> {
+    let rate = calc::parse_literal(rate.trim())
+        .map_err(|_| anyhow::anyhow!("invalid rate `{}` for {}", rate.trim(), code))?;
+    if rate.is_zero() {
+        anyhow::bail!("rate for {} is zero", code);
+    }
+
+    Ok(rate)
+}
+
+impl Rates {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        if is_json {
+            Self::from_json(&contents)
+        } else {
+            Self::from_csv(&contents)
+        }
+    }
+
+    fn from_json(contents: &str) -> anyhow::Result<Self> {
+        let file: RatesFile = serde_json::from_str(contents)?;
+
+        let mut rates = HashMap::new();
+        for (code, rate) in file.rates {
+            // keep the exact decimal text instead of going through f64
+            let rate = match rate {
+                serde_json::Value::String(rate) => rate,
+                rate => rate.to_string(),
+            };
+            rates.insert(code.to_uppercase(), parse_rate(&code, &rate)?);
+        }
+
+        if let Some(base) = file.base {
+            rates
+                .entry(base.to_uppercase())
+                .or_insert_with(|| BigRational::from_integer(1.into()));
+        }
+
+        Ok(Self {
+            date: file.date,
+            rates,
+        })
+    }
+
+    // currency,rate[,date] - a header row and `#` comments are skipped
+    fn from_csv(contents: &str) -> anyhow::Result<Self> {
+        let mut rates = HashMap::new();
+        let mut date = None;
+
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let columns: Vec<&str> = line.split(',').map(str::trim).collect();
+            if columns.len() < 2 || columns[0].eq_ignore_ascii_case("currency") {
+                continue;
+            }
+
+            rates.insert(
+                columns[0].to_uppercase(),
+                parse_rate(columns[0], columns[1])?,
+            );
+            if let Some(row_date) = columns.get(2).filter(|d| !d.is_empty()) {
+                date = date.max(Some(row_date.to_string()));
+            }
+        }
+
+        Ok(Self { date, rates })
+    }
+
+    /// Splits `<amount> <from> in|to <to>` if both codes are known.
+    pub fn parse_query<'a>(&self, input: &'a str) -> Option<(&'a str, String, String)> {
+        let lowercase = input.to_ascii_lowercase();
+        let split = lowercase
+            .rfind(" in ")
+            .or_else(|| lowercase.rfind(" to "))?;
+
+        let to = input[split + 4..].trim().to_uppercase();
+        let amount = input[..split].trim_end();
+        let from_start = amount
+            .char_indices()
+            .rev()
+            .find(|(_, c)| !c.is_alphabetic())
+            .map(|(i, c)| i + c.len_utf8())?;
+        let from = amount[from_start..].to_uppercase();
+
+        if self.rates.contains_key(&from) && self.rates.contains_key(&to) {
+            Some((&amount[..from_start], from, to))
+        } else {
+            None
+        }
+    }
+
+    pub fn rate(&self, from: &str, to: &str) -> Option<BigRational> {
+        Some(self.rates.get(to)? / self.rates.get(from)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queries() {
+        let rates = Rates::from_json(r#"{ "rates": { "EUR": 1, "USD": "1.08" } }"#).unwrap();
+
+        assert_eq!(
+            rates.parse_query("100 usd in eur"),
+            Some(("100 ", "USD".to_string(), "EUR".to_string()))
+        );
+        assert_eq!(
+            rates.parse_query("(2+3)EUR to Usd"),
+            Some(("(2+3)", "EUR".to_string(), "USD".to_string()))
+        );
+        assert_eq!(rates.parse_query("100 gbp in eur"), None);
+        assert_eq!(rates.parse_query("100 usd"), None);
+
+        // a symbol that's more than one byte right before the code
+        assert_eq!(
+            rates.parse_query("5€eur in usd"),
+            Some(("5€", "EUR".to_string(), "USD".to_string()))
+        );
+        assert_eq!(rates.parse_query("5€ in usd"), None);
+        assert_eq!(rates.parse_query("5 € to usd"), None);
+    }
+}
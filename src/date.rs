@@ -0,0 +1,451 @@
+// date/time arithmetic for calculator mode:
+// `today + 90d`, `2026-12-25 - today`, `@1700000000`, `now in Asia/Tokyo`
+
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, SecondsFormat,
+    TimeZone, Utc,
+};
+use chrono_tz::{Tz, TZ_VARIANTS};
+
+use crate::calc::Output;
+
+#[derive(Clone, Copy)]
+enum Zone {
+    Local,
+    Named(Tz),
+}
+
+enum Value {
+    Time(DateTime<Utc>),
+    Duration(Duration),
+}
+
+enum Token {
+    Time(DateTime<Utc>),
+    Duration(Duration),
+    Plus,
+    Minus,
+}
+
+const KEYWORDS: [&str; 4] = ["now", "today", "tomorrow", "yesterday"];
+
+/// Returns true if the input looks like it's meant for the date evaluator. Keywords
+/// like `now` don't count when there's a calculator variable with that name.
+pub fn is_date_expr(input: &str, is_variable: impl Fn(&str) -> bool) -> bool {
+    let input = input.trim().to_lowercase();
+
+    let words: Vec<&str> = input
+        .split(|c: char| c.is_whitespace() || c == '+' || c == '-')
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    input.starts_with('@')
+        || (!words.is_empty() && words.iter().all(|word| parse_duration(word).is_some()))
+        || input
+            .split(|c: char| !c.is_alphabetic())
+            .any(|word| KEYWORDS.contains(&word) && !is_variable(word))
+        || input.split_whitespace().any(|word| {
+            word.get(..10)
+                .is_some_and(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok())
+        })
+}
+
+fn midnight(date: NaiveDate) -> NaiveDateTime {
+    date.and_hms_opt(0, 0, 0).unwrap()
+}
+
+// a time in the hour skipped when the clocks go forward doesn't exist, so it's taken
+// as the one an hour later, which is what the clock would show
+fn from_local<Z: TimeZone>(zone: &Z, time: NaiveDateTime) -> Option<DateTime<Utc>> {
+    zone.from_local_datetime(&time)
+        .earliest()
+        .or_else(|| {
+            let later = time.checked_add_signed(Duration::hours(1))?;
+            zone.from_local_datetime(&later).earliest()
+        })
+        .map(|time| time.with_timezone(&Utc))
+}
+
+fn local_midnight(date: NaiveDate) -> DateTime<Utc> {
+    from_local(&Local, midnight(date)).unwrap_or_else(|| Utc.from_utc_datetime(&midnight(date)))
+}
+
+fn keyword(word: &str) -> Option<DateTime<Utc>> {
+    let today = Local::now().date_naive();
+
+    match word {
+        "now" => Some(Utc::now()),
+        "today" => Some(local_midnight(today)),
+        "tomorrow" => today.succ_opt().map(local_midnight),
+        "yesterday" => today.pred_opt().map(local_midnight),
+        _ => None,
+    }
+}
+
+// ISO-8601 dates and datetimes, local time unless an offset is given
+fn parse_time(word: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::<FixedOffset>::parse_from_rfc3339(word) {
+        return Some(time.with_timezone(&Utc));
+    }
+
+    if let Some(naive) = word.strip_suffix(['z', 'Z']) {
+        if let Some(time) = parse_naive(naive) {
+            return Some(Utc.from_utc_datetime(&time));
+        }
+    }
+
+    from_local(&Local, parse_naive(word)?)
+}
+
+fn parse_naive(word: &str) -> Option<NaiveDateTime> {
+    for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(word, format) {
+            return Some(time);
+        }
+    }
+
+    NaiveDate::parse_from_str(word, "%Y-%m-%d")
+        .ok()
+        .map(midnight)
+}
+
+// `90d`, `2w`, `1h30m`. `None` when it's too long for a Duration, since this runs on
+// every key press
+fn parse_duration(word: &str) -> Option<Duration> {
+    let mut total = Duration::zero();
+    let mut digits = String::new();
+
+    for c in word.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+
+        let n: i64 = digits.parse().ok()?;
+        digits.clear();
+
+        let duration = match c {
+            'w' => Duration::try_weeks(n),
+            'd' => Duration::try_days(n),
+            'h' => Duration::try_hours(n),
+            'm' => Duration::try_minutes(n),
+            's' => Duration::try_seconds(n),
+            _ => return None,
+        };
+        total = total.checked_add(&duration?)?;
+    }
+
+    if digits.is_empty() && !word.is_empty() {
+        Some(total)
+    } else {
+        None
+    }
+}
+
+fn find_zone(name: &str) -> anyhow::Result<Zone> {
+    let name = name.trim().replace(' ', "_");
+
+    match name.to_lowercase().as_str() {
+        "local" => return Ok(Zone::Local),
+        "utc" | "gmt" | "z" => return Ok(Zone::Named(Tz::UTC)),
+        _ => {}
+    }
+
+    // full names first ("Asia/Tokyo"), then just the city ("tokyo")
+    TZ_VARIANTS
+        .iter()
+        .find(|tz| tz.name().eq_ignore_ascii_case(&name))
+        .or_else(|| {
+            TZ_VARIANTS.iter().find(|tz| {
+                tz.name()
+                    .rsplit('/')
+                    .next()
+                    .is_some_and(|city| city.eq_ignore_ascii_case(&name))
+            })
+        })
+        .map(|tz| Zone::Named(*tz))
+        .ok_or_else(|| anyhow::anyhow!("unknown time zone `{}`", name))
+}
+
+fn tokenize(input: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = input.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        } else if c == '+' {
+            tokens.push(Token::Plus);
+            i += 1;
+            continue;
+        }
+
+        // a '-' is an operator unless it's inside a date, which words never start with
+        if c == '-' {
+            tokens.push(Token::Minus);
+            i += 1;
+            continue;
+        }
+
+        let start = i;
+        while i < chars.len() && !chars[i].is_whitespace() {
+            // '-' right after a digit is part of a date in the first 10 characters
+            // (2026-12-25), after that only a utc offset (T10:00-05:00) has a sign
+            let after_digit = i > start && chars[i - 1].is_ascii_digit();
+            let has_time = chars[start..i].contains(&'T');
+            if chars[i] == '+' && !(after_digit && has_time) {
+                break;
+            }
+            if chars[i] == '-' && !(after_digit && (i - start < 10 || has_time)) {
+                break;
+            }
+            i += 1;
+        }
+
+        let word: String = chars[start..i].iter().collect();
+        let lowercase = word.to_lowercase();
+
+        let token = if let Some(timestamp) = word.strip_prefix('@') {
+            let seconds: i64 = timestamp
+                .parse()
+                .map_err(|_| anyhow::anyhow!("invalid unix timestamp `{}`", timestamp))?;
+            Token::Time(
+                Utc.timestamp_opt(seconds, 0)
+                    .single()
+                    .ok_or_else(|| anyhow::anyhow!("timestamp out of range"))?,
+            )
+        } else if let Some(time) = keyword(&lowercase).or_else(|| parse_time(&word)) {
+            Token::Time(time)
+        } else if let Some(duration) = parse_duration(&lowercase) {
+            Token::Duration(duration)
+        } else {
+            anyhow::bail!("don't know what `{}` means", word);
+        };
+
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+fn apply(lhs: Value, plus: bool, rhs: Value) -> anyhow::Result<Value> {
+    let overflow = || anyhow::anyhow!("date out of range");
+
+    Ok(match (lhs, rhs) {
+        (Value::Time(t), Value::Duration(d)) if plus => {
+            Value::Time(t.checked_add_signed(d).ok_or_else(overflow)?)
+        }
+        (Value::Time(t), Value::Duration(d)) => {
+            Value::Time(t.checked_sub_signed(d).ok_or_else(overflow)?)
+        }
+        (Value::Duration(d), Value::Time(t)) if plus => {
+            Value::Time(t.checked_add_signed(d).ok_or_else(overflow)?)
+        }
+        (Value::Time(a), Value::Time(b)) if !plus => Value::Duration(a - b),
+        (Value::Duration(a), Value::Duration(b)) if plus => {
+            Value::Duration(a.checked_add(&b).ok_or_else(overflow)?)
+        }
+        (Value::Duration(a), Value::Duration(b)) => {
+            Value::Duration(a.checked_sub(&b).ok_or_else(overflow)?)
+        }
+        _ => anyhow::bail!("can't {} those", if plus { "add" } else { "subtract" }),
+    })
+}
+
+fn time_outputs<Z: TimeZone>(time: DateTime<Z>) -> Vec<Output>
+where
+    Z::Offset: std::fmt::Display,
+{
+    let mut formats = vec![
+        time.to_rfc3339_opts(SecondsFormat::Secs, true),
+        time.format("%A, %-d %B %Y %H:%M:%S %Z").to_string(),
+        time.format("%Y-%m-%d").to_string(),
+        time.timestamp().to_string(),
+    ];
+    // rfc 2822 only has four digit years, chrono panics on any other
+    if (0..=9999).contains(&time.year()) {
+        formats.push(time.to_rfc2822());
+    }
+
+    formats
+        .into_iter()
+        .map(|text| Output {
+            display: text.clone(),
+            value: text,
+        })
+        .collect()
+}
+
+fn plural(n: i64, unit: &str) -> String {
+    if n == 1 {
+        format!("{} {}", n, unit)
+    } else {
+        format!("{} {}s", n, unit)
+    }
+}
+
+fn duration_outputs(duration: Duration) -> Vec<Output> {
+    let sign = if duration < Duration::zero() { "-" } else { "" };
+    let seconds = duration.num_seconds().abs();
+
+    let (days, rem) = (seconds / 86_400, seconds % 86_400);
+    let (hours, rem) = (rem / 3_600, rem % 3_600);
+    let (minutes, secs) = (rem / 60, rem % 60);
+
+    let mut parts = Vec::new();
+    for (n, unit) in [(days, "d"), (hours, "h"), (minutes, "m"), (secs, "s")] {
+        if n != 0 {
+            parts.push(format!("{}{}", n, unit));
+        }
+    }
+    if parts.is_empty() {
+        parts.push("0s".to_string());
+    }
+
+    let formats = [
+        format!("{}{}", sign, parts.join(" ")),
+        format!("{}{}", sign, plural(duration.num_days().abs(), "day")),
+        format!("{}{}", sign, plural(duration.num_hours().abs(), "hour")),
+        format!("{}{}", sign, plural(seconds, "second")),
+    ];
+
+    formats
+        .into_iter()
+        .map(|text| Output {
+            display: text.clone(),
+            value: text,
+        })
+        .collect()
+}
+
+fn operand(token: Option<Token>) -> anyhow::Result<Value> {
+    match token {
+        Some(Token::Time(t)) => Ok(Value::Time(t)),
+        Some(Token::Duration(d)) => Ok(Value::Duration(d)),
+        Some(_) => anyhow::bail!("expected a date or a duration"),
+        None => anyhow::bail!("unexpected end of expression"),
+    }
+}
+
+pub fn eval(input: &str) -> anyhow::Result<Vec<Output>> {
+    // `... in Europe/Berlin` / `... to utc` picks the zone the result is shown in
+    let lowercase = input.to_ascii_lowercase();
+    let (expr, zone) = match lowercase.rfind(" in ").or_else(|| lowercase.rfind(" to ")) {
+        Some(i) => (&input[..i], find_zone(&input[i + 4..])?),
+        None => (input, Zone::Local),
+    };
+
+    let mut tokens = tokenize(expr)?.into_iter();
+    let mut value = operand(tokens.next())?;
+
+    while let Some(op) = tokens.next() {
+        let plus = match op {
+            Token::Plus => true,
+            Token::Minus => false,
+            _ => anyhow::bail!("expected `+` or `-` between values"),
+        };
+
+        value = apply(value, plus, operand(tokens.next())?)?;
+    }
+
+    Ok(match (value, zone) {
+        (Value::Time(t), Zone::Local) => time_outputs(t.with_timezone(&Local)),
+        (Value::Time(t), Zone::Named(tz)) => time_outputs(t.with_timezone(&tz)),
+        (Value::Duration(d), _) => duration_outputs(d),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first(input: &str) -> String {
+        eval(input).unwrap().remove(0).value
+    }
+
+    #[test]
+    fn detects_date_expressions() {
+        let no_vars = |_: &str| false;
+        assert!(is_date_expr("today + 90d", no_vars));
+        assert!(is_date_expr("2026-12-25 - today", no_vars));
+        assert!(is_date_expr("@1700000000", no_vars));
+        assert!(is_date_expr("1h30m + 15m", no_vars));
+        assert!(!is_date_expr("1 + 2", no_vars));
+        assert!(!is_date_expr("2026 - 12", no_vars));
+    }
+
+    #[test]
+    fn variables_shadow_keywords() {
+        let now_is_a_var = |name: &str| name == "now";
+        assert!(!is_date_expr("now * 2", now_is_a_var));
+        assert!(is_date_expr("today + 1d", now_is_a_var));
+        // an actual date still makes it a date expression
+        assert!(is_date_expr("2026-12-25 - now", now_is_a_var));
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(first("@0 in utc"), "1970-01-01T00:00:00Z");
+        assert_eq!(first("@1700000000 to utc"), "2023-11-14T22:13:20Z");
+        assert_eq!(
+            first("2026-12-25T00:00Z + 1w in utc"),
+            "2027-01-01T00:00:00Z"
+        );
+        assert_eq!(first("2026-12-25 - 2026-12-24"), "1d");
+        assert_eq!(first("1h30m + 15m"), "1h 45m");
+        assert_eq!(
+            first("2026-01-01T10:00:00-05:00 in utc"),
+            "2026-01-01T15:00:00Z"
+        );
+        assert!(eval("2026-12-25 + 2026-12-24").is_err());
+        assert!(eval("today in Nowhere/Special").is_err());
+    }
+
+    #[test]
+    fn durations_right_after_a_date() {
+        assert_eq!(first("2026-12-25-1d"), first("2026-12-24"));
+        assert_eq!(first("2026-12-25+1d"), first("2026-12-26"));
+        assert_eq!(first("2026-12-25T00:00Z-1h in utc"), "2026-12-24T23:00:00Z");
+    }
+
+    #[test]
+    fn out_of_range() {
+        let no_vars = |_: &str| false;
+        assert!(!is_date_expr("99999999999999w", no_vars));
+        assert!(parse_duration("99999999999999w").is_none());
+        assert!(parse_duration("9223372036854775807s").is_none());
+        assert!(parse_duration("99999999999999999999d").is_none());
+
+        // each fits, but not together
+        // a Duration goes up to i64::MAX milliseconds
+        let half = i64::MAX / 1000 / 2 + 1;
+        assert!(parse_duration(&format!("{0}s{0}s", half)).is_none());
+        assert!(eval(&format!("{0}s + {0}s", half)).is_err());
+
+        assert!(eval("today + 100000000d").is_err());
+        assert!(eval("today - 100000000d").is_err());
+        // far off, but still a date
+        assert!(first("@0 + 10000000w in utc").starts_with("+193623-"));
+    }
+
+    #[test]
+    fn skipped_local_times() {
+        // clocks in new york went from 2:00 to 3:00 that night
+        let zone = chrono_tz::America::New_York;
+        let skipped = parse_naive("2026-03-08T02:30").unwrap();
+        assert_eq!(
+            from_local(&zone, skipped).unwrap(),
+            Utc.with_ymd_and_hms(2026, 3, 8, 7, 30, 0).unwrap()
+        );
+
+        let normal = parse_naive("2026-03-08T01:30").unwrap();
+        assert_eq!(
+            from_local(&zone, normal).unwrap(),
+            Utc.with_ymd_and_hms(2026, 3, 8, 6, 30, 0).unwrap()
+        );
+    }
+}
@@ -0,0 +1,393 @@
+// `f ` mode: searches files under the `[files]` roots. the index is built on a
+// background thread and saved to the cache dir, so there's something to search right
+// after startup while the fresh one is being built. matching runs on another thread so
+// typing doesn't wait on it
+
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, RwLock};
+
+use directories::ProjectDirs;
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use globset::GlobSet;
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+use crate::ignore::build_globs;
+
+// keeps memory and per-keystroke matching time in check on huge roots
+const MAX_FILES: usize = 200_000;
+
+pub struct Filter {
+    include: GlobSet,
+    exclude: GlobSet,
+    extensions: Vec<String>,
+}
+
+impl Filter {
+    pub fn new(config: &config::Files) -> anyhow::Result<Self> {
+        Ok(Self {
+            include: build_globs(&config.include)?,
+            exclude: build_globs(&config.exclude)?,
+            extensions: config
+                .extensions
+                .iter()
+                .map(|ext| ext.trim_start_matches('.').to_lowercase())
+                .collect(),
+        })
+    }
+
+    // `relative` is the path under its root with `/` separators
+    fn excludes(&self, relative: &str) -> bool {
+        self.exclude.is_match(relative)
+    }
+
+    fn includes_file(&self, relative: &str, path: &Path) -> bool {
+        let included = self.include.is_empty() || self.include.is_match(relative);
+        let extension_ok = self.extensions.is_empty()
+            || path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| self.extensions.contains(&ext.to_lowercase()));
+
+        included && extension_ok && !self.excludes(relative)
+    }
+}
+
+fn cache_path() -> PathBuf {
+    let project_dir = ProjectDirs::from("", "", "tistow").expect("couldn't get project dir");
+    project_dir.cache_dir().join("files.json")
+}
+
+#[derive(Serialize, Deserialize)]
+struct Cache {
+    // cache_key of the config it was built with
+    config: u64,
+    files: Vec<PathBuf>,
+}
+
+// the saved index is thrown away when the roots, patterns or depth change
+fn cache_key(config: &config::Files) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    config.roots.hash(&mut hasher);
+    config.include.hash(&mut hasher);
+    config.exclude.hash(&mut hasher);
+    config.extensions.hash(&mut hasher);
+    config.max_depth.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn load_cache(key: u64) -> Option<Vec<PathBuf>> {
+    let contents = std::fs::read_to_string(cache_path()).ok()?;
+    let cache: Cache = serde_json::from_str(&contents).ok()?;
+    (cache.config == key).then_some(cache.files)
+}
+
+fn save_cache(key: u64, files: &[PathBuf]) -> anyhow::Result<()> {
+    let path = cache_path();
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    let cache = Cache {
+        config: key,
+        files: files.to_vec(),
+    };
+    std::fs::write(path, serde_json::to_string(&cache)?)?;
+    Ok(())
+}
+
+fn build_index(config: &config::Files, filter: &Filter) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+
+    for root in &config.roots {
+        let root = PathBuf::from(
+            shellexpand::env(root)
+                .expect("couldn't get file search root")
+                .as_ref(),
+        );
+        let relative = |path: &Path| {
+            path.strip_prefix(&root)
+                .unwrap_or(path)
+                .to_string_lossy()
+                .replace('\\', "/")
+        };
+
+        let walker = walkdir::WalkDir::new(&root)
+            .max_depth(config.max_depth)
+            .into_iter()
+            // skip whole excluded directories instead of walking them
+            .filter_entry(|entry| entry.depth() == 0 || !filter.excludes(&relative(entry.path())));
+
+        for entry in walker.filter_map(Result::ok) {
+            if entry.file_type().is_file()
+                && filter.includes_file(&relative(entry.path()), entry.path())
+            {
+                files.push(entry.into_path());
+                if files.len() >= MAX_FILES {
+                    println!("file index is full, stopping at {} files", MAX_FILES);
+                    return files;
+                }
+            }
+        }
+    }
+
+    files
+}
+
+// files whose name matches before files that only match on their path, with the char
+// indices into the name that matched
+fn find(
+    matcher: &SkimMatcherV2,
+    files: &[PathBuf],
+    query: &str,
+    limit: usize,
+) -> Vec<(PathBuf, Vec<usize>)> {
+    let mut matches: Vec<(u8, i64, &PathBuf)> = files
+        .iter()
+        .filter_map(|path| {
+            let name = path.file_name()?.to_str()?;
+            if let Some(score) = matcher.fuzzy_match(name, query) {
+                return Some((0, score, path));
+            }
+
+            let score = matcher.fuzzy_match(path.to_str()?, query)?;
+            Some((1, score, path))
+        })
+        .collect();
+
+    matches.sort_by_key(|(tier, score, path)| (*tier, -score, path.as_os_str().len()));
+
+    // only worth finding where it matched for the ones that are shown
+    let name_indices = |tier: u8, path: &Path| -> Option<Vec<usize>> {
+        let name = path.file_name()?.to_str()?;
+        if tier == 0 {
+            return Some(matcher.fuzzy_indices(name, query)?.1);
+        }
+
+        // the part of the path match that landed in the name
+        let full = path.to_str()?;
+        let name_start = full.chars().count() - name.chars().count();
+        let (_, indices) = matcher.fuzzy_indices(full, query)?;
+        Some(
+            indices
+                .into_iter()
+                .filter_map(|i| i.checked_sub(name_start))
+                .collect(),
+        )
+    };
+
+    matches
+        .into_iter()
+        .take(limit)
+        .map(|(tier, _, path)| {
+            let indices = name_indices(tier, path).unwrap_or_default();
+            (path.clone(), indices)
+        })
+        .collect()
+}
+
+pub struct FileIndex {
+    enabled: bool,
+    indexed: Arc<AtomicBool>,
+    // `None` asks for the last query again, after the index changed
+    queries_tx: mpsc::Sender<Option<String>>,
+    found_rx: mpsc::Receiver<Vec<(PathBuf, Vec<usize>)>>,
+    // the last query sent off and the last matches that came back, which can be for
+    // the query before it while it's still matching
+    requested: RefCell<String>,
+    found: RefCell<Vec<(PathBuf, Vec<usize>)>>,
+}
+
+impl FileIndex {
+    /// Loads the saved index and starts keeping it up to date in the background, along
+    /// with a thread that finds the best `limit` matches for each query.
+    pub fn start(config: &config::Files, limit: usize, ctx: egui::Context) -> Self {
+        let enabled = !config.roots.is_empty();
+        let key = cache_key(config);
+        let files = if enabled { load_cache(key) } else { None };
+        let indexed = Arc::new(AtomicBool::new(files.is_some()));
+        let files = Arc::new(RwLock::new(files));
+        let (queries_tx, queries_rx) = mpsc::channel::<Option<String>>();
+        let (found_tx, found_rx) = mpsc::channel();
+
+        if enabled {
+            std::thread::spawn({
+                let config = config.clone();
+                let files = files.clone();
+                let indexed = indexed.clone();
+                let queries_tx = queries_tx.clone();
+                move || {
+                    // checked when the config was loaded
+                    let filter = Filter::new(&config).unwrap();
+
+                    loop {
+                        let index = build_index(&config, &filter);
+                        println!("indexed {} files", index.len());
+                        if let Err(e) = save_cache(key, &index) {
+                            println!("couldn't save file index: {}", e);
+                        }
+                        *files.write().unwrap() = Some(index);
+                        indexed.store(true, Ordering::Relaxed);
+                        // match the current query against the new index
+                        queries_tx.send(None).ok();
+
+                        if config.refresh_minutes == 0 {
+                            break;
+                        }
+                        let refresh = std::time::Duration::from_secs(config.refresh_minutes * 60);
+                        std::thread::sleep(refresh);
+                    }
+                }
+            });
+
+            std::thread::spawn(move || {
+                let matcher = SkimMatcherV2::default().ignore_case();
+                let mut query = None;
+
+                while let Ok(request) = queries_rx.recv() {
+                    // skip to the newest one when typing got ahead of matching
+                    for request in std::iter::once(request).chain(queries_rx.try_iter()) {
+                        query = request.or(query);
+                    }
+
+                    let files = files.read().unwrap();
+                    let (files, query) = match (files.as_ref(), &query) {
+                        (Some(files), Some(query)) => (files, query),
+                        _ => continue,
+                    };
+                    if found_tx.send(find(&matcher, files, query, limit)).is_err() {
+                        break;
+                    }
+                    ctx.request_repaint();
+                }
+            });
+        }
+
+        Self {
+            enabled,
+            indexed,
+            queries_tx,
+            found_rx,
+            requested: RefCell::new(String::new()),
+            found: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Best matches for `query`, files whose name matches before files that only match
+    /// on their path, with the char indices into the file name that matched. Until
+    /// they're found, the matches for the query before it. `None` while the first index
+    /// is still being built.
+    pub fn find(&self, query: &str) -> Option<Vec<(PathBuf, Vec<usize>)>> {
+        if *self.requested.borrow() != query {
+            self.requested.replace(query.to_string());
+            self.queries_tx.send(Some(query.to_string())).ok();
+        }
+        if let Some(found) = self.found_rx.try_iter().last() {
+            self.found.replace(found);
+        }
+
+        self.indexed
+            .load(Ordering::Relaxed)
+            .then(|| self.found.borrow().clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files_config(root: &Path, include: &[&str], exclude: &[&str]) -> config::Files {
+        config::Files {
+            roots: vec![root.display().to_string()],
+            include: include.iter().map(|p| p.to_string()).collect(),
+            exclude: exclude.iter().map(|p| p.to_string()).collect(),
+            extensions: vec![],
+            max_depth: 8,
+            refresh_minutes: 0,
+        }
+    }
+
+    #[test]
+    fn patterns() {
+        let config = files_config(Path::new("/"), &["**/*.pdf"], &["node_modules/*"]);
+        let filter = Filter::new(&config).unwrap();
+
+        // `*` stops at a `/`, and only the top level node_modules is meant
+        assert!(filter.excludes("node_modules/pkg"));
+        assert!(filter.excludes("Node_Modules/index.js"));
+        assert!(!filter.excludes("node_modules/pkg/index.js"));
+        assert!(!filter.excludes("app/node_modules/pkg"));
+
+        let report = Path::new("Work/Report.PDF");
+        assert!(filter.includes_file("Work/Report.PDF", report));
+        assert!(!filter.includes_file("node_modules/Report.pdf", report));
+        assert!(!filter.includes_file("Work/notes.txt", Path::new("Work/notes.txt")));
+    }
+
+    #[test]
+    fn index_skips_excluded_folders() {
+        let root = std::env::temp_dir().join("tistow-files-index");
+        let _ = std::fs::remove_dir_all(&root);
+        for folder in ["node_modules/pkg", "app/node_modules", "Docs"] {
+            std::fs::create_dir_all(root.join(folder)).unwrap();
+        }
+        for file in [
+            "node_modules/top.js",
+            "node_modules/pkg/nested.js",
+            "app/node_modules/kept.js",
+            "app/main.js",
+            "Docs/readme.md",
+        ] {
+            std::fs::write(root.join(file), "").unwrap();
+        }
+
+        // node_modules/pkg is excluded itself, so nothing under it is walked
+        let config = files_config(&root, &["**/*.JS"], &["node_modules/*"]);
+        let mut found: Vec<String> = build_index(&config, &Filter::new(&config).unwrap())
+            .iter()
+            .map(|path| {
+                path.strip_prefix(&root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/")
+            })
+            .collect();
+        found.sort();
+        assert_eq!(found, ["app/main.js", "app/node_modules/kept.js"]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn find_highlights() {
+        let matcher = SkimMatcherV2::default().ignore_case();
+        let files = vec![
+            PathBuf::from("/docs/İstanbul Report.pdf"),
+            PathBuf::from("/docs/reports/summary.txt"),
+            PathBuf::from("/docs/other.txt"),
+        ];
+
+        let found = find(&matcher, &files, "REPORT", 10);
+        let paths: Vec<&PathBuf> = found.iter().map(|(path, _)| path).collect();
+        assert_eq!(paths, [&files[0], &files[1]]);
+
+        // indices into the name as it's shown, not its lowercased form
+        let name: Vec<char> = "İstanbul Report.pdf".chars().collect();
+        let matched: String = found[0].1.iter().map(|&i| name[i]).collect();
+        assert_eq!(matched, "Report");
+
+        // only matched on the folder, nothing in the name to highlight
+        assert!(found[1].1.is_empty());
+
+        let found = find(&matcher, &files, "repsum", 10);
+        assert_eq!(found[0].0, files[1]);
+        assert_eq!(found[0].1, [0, 1, 2]);
+
+        assert_eq!(find(&matcher, &files, "txt", 1).len(), 1);
+    }
+}
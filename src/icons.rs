@@ -0,0 +1,515 @@
+// icons next to the results. they're extracted on a background thread, since pulling
+// them out of an exe or converting an .icns is way too slow for the ui thread, and saved
+// as small pngs in the cache dir so startup doesn't have to do it all again
+//
+// windows: the icon location of a .lnk, the exe's own resources, or the shell's icon for
+// the file type. macos: the bundle's .icns, converted with sips
+//
+// files and folders in file and path results share one icon per type, and textures for
+// results that haven't been drawn in a while are dropped again
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use directories::ProjectDirs;
+use egui::{ColorImage, TextureHandle, TextureId};
+
+// bigger than they're drawn, so they stay sharp on hidpi screens
+#[cfg(target_os = "macos")]
+const ICON_SIZE: u32 = 32;
+// textures kept around for results that aren't drawn anymore, e.g. after browsing a few
+// big folders
+const MAX_TEXTURES: usize = 256;
+// files that have an icon of their own, anything else looks like the rest of its type
+const OWN_ICON_EXTENSIONS: &[&str] = &["exe", "dll", "ico", "lnk", "url", "cpl", "scr", "app"];
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum IconKind {
+    // the file's own icon
+    File,
+    // what the shell shows for any file with the same extension as `path`
+    FileType,
+    // what the shell shows for a folder
+    Folder,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub struct IconSource {
+    // just a name to go by for a FileType or Folder
+    pub path: PathBuf,
+    // which icon in an exe or dll, negative for a resource id
+    pub index: i32,
+    pub kind: IconKind,
+}
+
+impl IconSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            index: 0,
+            kind: IconKind::File,
+        }
+    }
+
+    /// For files and folders in file and path results, which mostly look like every
+    /// other file of their type, so they share one icon instead of each getting their
+    /// own extracted and cached.
+    pub fn for_file(path: &Path, is_dir: bool) -> Self {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        if OWN_ICON_EXTENSIONS.contains(&extension.as_str()) {
+            Self::new(path)
+        } else if is_dir {
+            Self {
+                kind: IconKind::Folder,
+                ..Self::new("folder")
+            }
+        } else {
+            Self {
+                kind: IconKind::FileType,
+                ..Self::new(format!("file.{}", extension))
+            }
+        }
+    }
+}
+
+struct Image {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+// keyed by the modification time too, so an updated app gets its new icon
+fn cache_path(source: &IconSource) -> PathBuf {
+    let modified = std::fs::metadata(&source.path)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    (source, modified).hash(&mut hasher);
+
+    let project_dir = ProjectDirs::from("", "", "tistow").expect("couldn't get project dir");
+    project_dir
+        .cache_dir()
+        .join("icons")
+        .join(format!("{:016x}.png", hasher.finish()))
+}
+
+fn load_png(path: &Path) -> anyhow::Result<Image> {
+    let mut decoder = png::Decoder::new(std::fs::File::open(path)?);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data)?;
+    data.truncate(info.buffer_size());
+
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => data,
+        png::ColorType::Rgb => data
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 255])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => data
+            .chunks_exact(2)
+            .flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]])
+            .collect(),
+        png::ColorType::Grayscale => data.iter().flat_map(|&g| [g, g, g, 255]).collect(),
+        color_type => anyhow::bail!("unexpected png color type {:?}", color_type),
+    };
+
+    Ok(Image {
+        width: info.width,
+        height: info.height,
+        rgba,
+    })
+}
+
+fn save_png(path: &Path, image: &Image) -> anyhow::Result<()> {
+    std::fs::create_dir_all(path.parent().unwrap())?;
+
+    let mut encoder = png::Encoder::new(std::fs::File::create(path)?, image.width, image.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&image.rgba)?;
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+unsafe fn icon_to_image(
+    icon: windows::Win32::UI::WindowsAndMessaging::HICON,
+) -> anyhow::Result<Image> {
+    use std::ffi::c_void;
+    use windows::Win32::{
+        Foundation::HWND,
+        Graphics::Gdi::{
+            DeleteObject, GetDC, GetDIBits, GetObjectW, ReleaseDC, BITMAP, BITMAPINFO,
+            BITMAPINFOHEADER, DIB_RGB_COLORS,
+        },
+        UI::WindowsAndMessaging::{GetIconInfo, ICONINFO},
+    };
+
+    let mut info = ICONINFO::default();
+    GetIconInfo(icon, &mut info).ok()?;
+
+    // GetIconInfo hands out copies of both bitmaps, which are ours to delete
+    let result = (|| {
+        // black and white icons only have a mask, those aren't worth the trouble
+        anyhow::ensure!(!info.hbmColor.is_invalid(), "icon has no color bitmap");
+
+        let mut bitmap = BITMAP::default();
+        GetObjectW(
+            info.hbmColor,
+            std::mem::size_of::<BITMAP>() as i32,
+            &mut bitmap as *mut BITMAP as *mut c_void,
+        );
+        let (width, height) = (bitmap.bmWidth, bitmap.bmHeight);
+        anyhow::ensure!(width > 0 && height > 0, "icon bitmap is empty");
+
+        let mut header = BITMAPINFO {
+            bmiHeader: BITMAPINFOHEADER {
+                biSize: std::mem::size_of::<BITMAPINFOHEADER>() as u32,
+                biWidth: width,
+                // negative for top-down rows
+                biHeight: -height,
+                biPlanes: 1,
+                biBitCount: 32,
+                biCompression: 0, // BI_RGB
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut bgra = vec![0u8; (width * height * 4) as usize];
+        let dc = GetDC(HWND(0));
+        let lines = GetDIBits(
+            dc,
+            info.hbmColor,
+            0,
+            height as u32,
+            bgra.as_mut_ptr() as *mut c_void,
+            &mut header,
+            DIB_RGB_COLORS,
+        );
+        ReleaseDC(HWND(0), dc);
+        anyhow::ensure!(lines == height, "couldn't read icon bitmap");
+
+        // icons from before alpha channels were a thing leave it all at zero
+        let has_alpha = bgra.chunks_exact(4).any(|pixel| pixel[3] != 0);
+        let rgba = bgra
+            .chunks_exact(4)
+            .flat_map(|pixel| {
+                let alpha = if has_alpha { pixel[3] } else { 255 };
+                [pixel[2], pixel[1], pixel[0], alpha]
+            })
+            .collect();
+
+        Ok(Image {
+            width: width as u32,
+            height: height as u32,
+            rgba,
+        })
+    })();
+
+    DeleteObject(info.hbmColor);
+    DeleteObject(info.hbmMask);
+    result
+}
+
+#[cfg(target_os = "windows")]
+fn extract(source: &IconSource) -> anyhow::Result<Option<Image>> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::{
+        core::PCWSTR,
+        Win32::{
+            Storage::FileSystem::{
+                FILE_ATTRIBUTE_DIRECTORY, FILE_ATTRIBUTE_NORMAL, FILE_FLAGS_AND_ATTRIBUTES,
+            },
+            UI::{
+                Shell::{
+                    ExtractIconExW, SHGetFileInfoW, SHFILEINFOW, SHGFI_ICON, SHGFI_LARGEICON,
+                    SHGFI_USEFILEATTRIBUTES,
+                },
+                WindowsAndMessaging::{DestroyIcon, HICON},
+            },
+        },
+    };
+
+    let path: Vec<u16> = source
+        .path
+        .as_os_str()
+        .encode_wide()
+        .chain(Some(0))
+        .collect();
+
+    unsafe {
+        let mut icon = HICON(0);
+        if source.kind == IconKind::File {
+            ExtractIconExW(
+                PCWSTR(path.as_ptr()),
+                source.index,
+                &mut icon,
+                std::ptr::null_mut(),
+                1,
+            );
+        }
+
+        // anything without icons of its own, like a document or a folder, gets the one
+        // explorer shows for it. for a whole type that goes by the name alone
+        if icon.is_invalid() {
+            let flags = SHGFI_ICON | SHGFI_LARGEICON;
+            let (attributes, flags) = match source.kind {
+                IconKind::File => (FILE_FLAGS_AND_ATTRIBUTES(0), flags),
+                IconKind::FileType => (FILE_ATTRIBUTE_NORMAL, flags | SHGFI_USEFILEATTRIBUTES),
+                IconKind::Folder => (FILE_ATTRIBUTE_DIRECTORY, flags | SHGFI_USEFILEATTRIBUTES),
+            };
+
+            let mut info = SHFILEINFOW::default();
+            SHGetFileInfoW(
+                PCWSTR(path.as_ptr()),
+                attributes,
+                &mut info,
+                std::mem::size_of::<SHFILEINFOW>() as u32,
+                flags,
+            );
+            icon = info.hIcon;
+        }
+
+        if icon.is_invalid() {
+            return Ok(None);
+        }
+
+        let image = icon_to_image(icon);
+        DestroyIcon(icon);
+        image.map(Some)
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn extract(source: &IconSource) -> anyhow::Result<Option<Image>> {
+    let icon_file = crate::bundle::read(&source.path)?.and_then(|bundle| bundle.icon_file);
+    let Some(icon_file) = icon_file else {
+        return Ok(None);
+    };
+
+    // CFBundleIconFile may or may not have the extension
+    let mut icns = source
+        .path
+        .join("Contents")
+        .join("Resources")
+        .join(icon_file);
+    if icns.extension().is_none() {
+        icns.set_extension("icns");
+    }
+    if !icns.exists() {
+        return Ok(None);
+    }
+
+    let png = std::env::temp_dir().join(format!("tistow-icon-{}.png", std::process::id()));
+    let size = ICON_SIZE.to_string();
+    let status = std::process::Command::new("sips")
+        .args(["-s", "format", "png", "--resampleHeightWidth", &size, &size])
+        .arg(&icns)
+        .arg("--out")
+        .arg(&png)
+        .stdout(std::process::Stdio::null())
+        .status()?;
+    anyhow::ensure!(status.success(), "sips failed on {}", icns.display());
+
+    let image = load_png(&png);
+    std::fs::remove_file(&png).ok();
+    image.map(Some)
+}
+
+// from the cache if it's there, `None` if the file has no icon
+fn load(source: &IconSource) -> anyhow::Result<Option<Image>> {
+    // quick to get, and they'd go stale when the default app for a type changes
+    if source.kind != IconKind::File {
+        return extract(source);
+    }
+
+    let cache_path = cache_path(source);
+    if let Ok(image) = load_png(&cache_path) {
+        return Ok(Some(image));
+    }
+
+    let image = extract(source)?;
+    if let Some(image) = &image {
+        if let Err(e) = save_png(&cache_path, image) {
+            println!("couldn't cache icon for {}: {}", source.path.display(), e);
+        }
+    }
+
+    Ok(image)
+}
+
+struct Texture {
+    // `None` while it's loading, or when there's no icon
+    handle: Option<TextureHandle>,
+    // the frame's `time` from egui when it was last drawn
+    last_used: f64,
+}
+
+pub struct Icons {
+    textures: RefCell<HashMap<IconSource, Texture>>,
+    requests_tx: mpsc::Sender<IconSource>,
+    loaded_rx: mpsc::Receiver<(IconSource, Option<Image>)>,
+}
+
+impl Icons {
+    pub fn new(ctx: egui::Context) -> Self {
+        let (requests_tx, requests_rx) = mpsc::channel::<IconSource>();
+        let (loaded_tx, loaded_rx) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            // SHGetFileInfoW wants com initialized on the thread it's called from
+            #[cfg(target_os = "windows")]
+            unsafe {
+                use windows::Win32::System::Com::{CoInitializeEx, COINIT_APARTMENTTHREADED};
+                CoInitializeEx(std::ptr::null(), COINIT_APARTMENTTHREADED).ok();
+            }
+
+            for source in requests_rx {
+                let image = load(&source).unwrap_or_else(|e| {
+                    println!("couldn't load icon for {}: {}", source.path.display(), e);
+                    None
+                });
+
+                if loaded_tx.send((source, image)).is_err() {
+                    break;
+                }
+                ctx.request_repaint();
+            }
+        });
+
+        Self {
+            textures: RefCell::new(HashMap::new()),
+            requests_tx,
+            loaded_rx,
+        }
+    }
+
+    /// The icon's texture, or `None` while it's still loading or if there isn't one.
+    /// The first call for an icon starts loading it.
+    pub fn get(&self, ctx: &egui::Context, source: &IconSource) -> Option<TextureId> {
+        let mut textures = self.textures.borrow_mut();
+        let now = ctx.input().time;
+
+        for (loaded, image) in self.loaded_rx.try_iter() {
+            let handle = image.map(|image| {
+                ctx.load_texture(
+                    loaded.path.display().to_string(),
+                    ColorImage::from_rgba_unmultiplied(
+                        [image.width as usize, image.height as usize],
+                        &image.rgba,
+                    ),
+                )
+            });
+            textures.insert(
+                loaded,
+                Texture {
+                    handle,
+                    last_used: now,
+                },
+            );
+        }
+
+        if let Some(texture) = textures.get_mut(source) {
+            texture.last_used = now;
+            return texture.handle.as_ref().map(TextureHandle::id);
+        }
+
+        textures.insert(
+            source.clone(),
+            Texture {
+                handle: None,
+                last_used: now,
+            },
+        );
+        self.requests_tx.send(source.clone()).ok();
+        Self::evict(&mut textures, now);
+        None
+    }
+
+    // drops the textures that were drawn longest ago, never ones drawn this frame
+    fn evict(textures: &mut HashMap<IconSource, Texture>, now: f64) {
+        let Some(excess) = textures.len().checked_sub(MAX_TEXTURES) else {
+            return;
+        };
+
+        let mut unused: Vec<(f64, IconSource)> = textures
+            .iter()
+            .filter(|(_, texture)| texture.last_used < now)
+            .map(|(source, texture)| (texture.last_used, source.clone()))
+            .collect();
+        unused.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        for (_, source) in unused.into_iter().take(excess) {
+            textures.remove(&source);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn icons_by_type() {
+        let a = IconSource::for_file(Path::new("/docs/a.PDF"), false);
+        let b = IconSource::for_file(Path::new("/other/b.pdf"), false);
+        assert_eq!(a, b);
+        assert_eq!(a.kind, IconKind::FileType);
+
+        let exe = IconSource::for_file(Path::new("/bin/tool.exe"), false);
+        assert_eq!(exe, IconSource::new("/bin/tool.exe"));
+
+        let docs = IconSource::for_file(Path::new("/docs"), true);
+        assert_eq!(docs, IconSource::for_file(Path::new("/other"), true));
+        assert_eq!(docs.kind, IconKind::Folder);
+        assert_ne!(
+            docs,
+            IconSource::for_file(Path::new("/docs/Makefile"), false)
+        );
+    }
+
+    #[test]
+    fn eviction() {
+        let mut textures = HashMap::new();
+        for i in 0..MAX_TEXTURES + 10 {
+            let texture = Texture {
+                handle: None,
+                last_used: if i < 20 { i as f64 } else { 100.0 },
+            };
+            textures.insert(IconSource::new(i.to_string()), texture);
+        }
+
+        Icons::evict(&mut textures, 100.0);
+        assert_eq!(textures.len(), MAX_TEXTURES);
+        assert!((0..10).all(|i| !textures.contains_key(&IconSource::new(i.to_string()))));
+        assert!((10..20).all(|i| textures.contains_key(&IconSource::new(i.to_string()))));
+
+        // everything drawn this frame stays, even over the limit
+        for texture in textures.values_mut() {
+            texture.last_used = 200.0;
+        }
+        textures.insert(
+            IconSource::new("new"),
+            Texture {
+                handle: None,
+                last_used: 200.0,
+            },
+        );
+        Icons::evict(&mut textures, 200.0);
+        assert_eq!(textures.len(), MAX_TEXTURES + 1);
+    }
+}
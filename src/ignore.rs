@@ -0,0 +1,556 @@
+// `ignore_paths` and the per-root include/exclude lists in `shortcut_paths`. every
+// pattern is matched case-insensitively, and how it's anchored depends on how it's written:
+//
+// - absolute paths like "${AppData}\...\Startup" match that path and everything under it
+// - patterns with a `/` in them like "Programs/Games" are anchored to the shortcut root
+// - bare names like "Uninstall *.lnk" match a file or folder at any depth
+// - "re:" regexes are matched against the whole path with `/` separators, and only
+//   anchored where they say so with ^ and $
+//
+// globs are gitignore-style, so `*` stops at a `/` and `**` doesn't, and a trailing `/`
+// only matches folders ("Games/" leaves a shortcut called Games alone). a leading `!`
+// un-ignores what an earlier pattern matched, and the last pattern that matches wins
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use regex::{Regex, RegexBuilder};
+
+use crate::config;
+
+enum Matcher {
+    Glob(GlobSet),
+    Regex(Regex),
+}
+
+pub struct Rule {
+    // as written in the config, for explain-ignore
+    pub pattern: String,
+    pub source: String,
+    pub negated: bool,
+    matcher: Matcher,
+    // matched against the full path instead of the path under the root
+    absolute: bool,
+}
+
+// `/` separators and no trailing slash, so patterns only have to deal with one form
+fn normalize(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    match path.trim_end_matches('/') {
+        "" => path,
+        trimmed => trimmed.to_string(),
+    }
+}
+
+// `~`, `$VAR` and `${VAR}` with any glob characters in what they expand to escaped, so
+// a `[` in a folder name doesn't start a character class
+fn expand(pattern: &str) -> anyhow::Result<String> {
+    let home = directories::BaseDirs::new()
+        .map(|dirs| PathBuf::from(globset::escape(&dirs.home_dir().to_string_lossy())));
+    let expanded = shellexpand::full_with_context(
+        pattern,
+        || home,
+        |var| std::env::var(var).map(|value| Some(globset::escape(&value))),
+    )?;
+
+    Ok(expanded.into_owned())
+}
+
+fn is_absolute(pattern: &str) -> bool {
+    let bytes = pattern.as_bytes();
+    pattern.starts_with('/')
+        || (bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':')
+}
+
+/// Globs the way every pattern in the config works: case-insensitive, and `*` stops at
+/// a `/`.
+pub fn build_globs(patterns: &[String]) -> anyhow::Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(
+            GlobBuilder::new(pattern)
+                .case_insensitive(true)
+                .literal_separator(true)
+                .build()?,
+        );
+    }
+
+    Ok(builder.build()?)
+}
+
+impl Rule {
+    pub fn new(pattern: &str, source: &str) -> anyhow::Result<Self> {
+        let (negated, rest) = match pattern.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, pattern),
+        };
+
+        let (matcher, absolute) = if let Some(regex) = rest.strip_prefix("re:") {
+            let regex = RegexBuilder::new(regex).case_insensitive(true).build()?;
+            (Matcher::Regex(regex), true)
+        } else {
+            let dir_only = rest.ends_with(['/', '\\']);
+            let glob = normalize(&expand(rest)?);
+            let absolute = is_absolute(&glob);
+
+            let glob = if absolute || glob.contains('/') {
+                glob.trim_start_matches("./").to_string()
+            } else {
+                format!("**/{}", glob)
+            };
+
+            // a matched folder takes everything in it along. only matching what's in it is
+            // the same as matching a folder, and never matches a file
+            let globs = if dir_only {
+                build_globs(&[format!("{}/**", glob)])?
+            } else {
+                build_globs(&[format!("{}/**", glob), glob])?
+            };
+            (Matcher::Glob(globs), absolute)
+        };
+
+        Ok(Self {
+            pattern: pattern.to_string(),
+            source: source.to_string(),
+            negated,
+            matcher,
+            absolute,
+        })
+    }
+
+    fn matches(&self, full: &str, relative: &str) -> bool {
+        let path = if self.absolute { full } else { relative };
+
+        match &self.matcher {
+            Matcher::Glob(globs) => globs.is_match(path),
+            Matcher::Regex(regex) => regex.is_match(path),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Rules(Vec<Rule>);
+
+impl Rules {
+    pub fn new(patterns: &[String], source: &str) -> anyhow::Result<Self> {
+        patterns
+            .iter()
+            .map(|pattern| {
+                Rule::new(pattern, source)
+                    .map_err(|e| anyhow::anyhow!("`{}` in {}: {}", pattern, source, e))
+            })
+            .collect::<anyhow::Result<_>>()
+            .map(Self)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The rule that decides whether the path is matched, the last one that matches it.
+    fn decide(&self, full: &str, relative: &str) -> Option<&Rule> {
+        self.0
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(full, relative))
+    }
+
+    fn is_match(&self, full: &str, relative: &str) -> bool {
+        self.decide(full, relative)
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+pub enum Verdict<'a> {
+    // by the rule, or by the rule on a folder it's in
+    Excluded(&'a Rule),
+    // the root has include patterns and none of them match
+    NotIncluded,
+    // with the `!` rule that un-ignored it, if any
+    Kept(Option<&'a Rule>),
+}
+
+pub struct RootFilter {
+    pub root: PathBuf,
+    // normalized, to find paths under it
+    prefix: String,
+    include: Rules,
+    exclude: Rules,
+}
+
+impl RootFilter {
+    pub fn new(search: &config::Search, root: &config::ShortcutRoot) -> anyhow::Result<Self> {
+        anyhow::ensure!(!root.path.is_empty(), "a shortcut_paths table has no path");
+        let path = shellexpand::full(&root.path)?.into_owned();
+
+        // global patterns first, so the root's own can override them
+        let mut exclude = Rules::new(&search.ignore_paths, "[search] ignore_paths")?;
+        exclude
+            .0
+            .extend(Rules::new(&root.exclude, &format!("excludes of {}", root.path))?.0);
+
+        Ok(Self {
+            prefix: normalize(&path),
+            root: PathBuf::from(path),
+            include: Rules::new(&root.include, &format!("includes of {}", root.path))?,
+            exclude,
+        })
+    }
+
+    pub fn all(search: &config::Search) -> anyhow::Result<Vec<Self>> {
+        search
+            .shortcut_paths
+            .iter()
+            .map(|path| Self::new(search, &path.root()))
+            .collect()
+    }
+
+    // the path under the root, `None` if it's not in it
+    fn relative(&self, full: &str) -> Option<String> {
+        let prefix = full.get(..self.prefix.len())?;
+        let rest = full.get(self.prefix.len()..)?;
+
+        if !prefix.eq_ignore_ascii_case(&self.prefix) {
+            return None;
+        }
+        if self.prefix.ends_with('/') {
+            return Some(rest.to_string());
+        }
+
+        rest.strip_prefix('/').map(str::to_string)
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.relative(&normalize(&path.to_string_lossy())).is_some()
+    }
+
+    fn paths(&self, path: &Path) -> (String, String) {
+        let full = normalize(&path.to_string_lossy());
+        let relative = self.relative(&full).unwrap_or_default();
+        (full, relative)
+    }
+
+    /// Whether the exclude patterns leave out a path found while walking the root.
+    pub fn excludes(&self, path: &Path) -> bool {
+        let (full, relative) = self.paths(path);
+        self.exclude.is_match(&full, &relative)
+    }
+
+    /// Whether a shortcut matches the root's include patterns, if it has any. These
+    /// aren't checked on the folders on the way down, they're meant for what's in them.
+    pub fn includes(&self, path: &Path) -> bool {
+        let (full, relative) = self.paths(path);
+        self.include.is_empty() || self.include.is_match(&full, &relative)
+    }
+
+    /// Why a file under the root is or isn't listed.
+    pub fn check(&self, path: &Path) -> Verdict<'_> {
+        // excluded folders aren't walked, so nothing in them can be un-ignored
+        let mut folders: Vec<&Path> = path
+            .ancestors()
+            .skip(1)
+            .take_while(|folder| self.contains(folder))
+            .collect();
+        folders.reverse();
+
+        for folder in folders {
+            let (full, relative) = self.paths(folder);
+            if let Some(rule) = self.exclude.decide(&full, &relative) {
+                if !rule.negated {
+                    return Verdict::Excluded(rule);
+                }
+            }
+        }
+
+        let (full, relative) = self.paths(path);
+        let unignored = match self.exclude.decide(&full, &relative) {
+            Some(rule) if !rule.negated => return Verdict::Excluded(rule),
+            rule => rule,
+        };
+
+        if !self.includes(path) {
+            return Verdict::NotIncluded;
+        }
+
+        Verdict::Kept(unignored)
+    }
+}
+
+/// Which shortcut roots `path` is under and which pattern decides whether it's listed,
+/// for `tistow explain-ignore <path>`.
+pub fn explain(search: &config::Search, path: &Path) -> anyhow::Result<String> {
+    let path = if path.is_relative() {
+        std::env::current_dir()?.join(path)
+    } else {
+        path.to_path_buf()
+    };
+    let mut out = String::new();
+    writeln!(out, "{}", path.display())?;
+
+    let mut found = false;
+    for filter in RootFilter::all(search)? {
+        if !filter.contains(&path) {
+            continue;
+        }
+        found = true;
+
+        writeln!(out, "  under {}", filter.root.display())?;
+        match filter.check(&path) {
+            Verdict::Excluded(rule) => writeln!(
+                out,
+                "    ignored by `{}` from {}",
+                rule.pattern, rule.source
+            )?,
+            Verdict::NotIncluded => writeln!(
+                out,
+                "    ignored, it doesn't match any of the root's include patterns"
+            )?,
+            Verdict::Kept(Some(rule)) => writeln!(
+                out,
+                "    not ignored, `{}` from {} un-ignores it",
+                rule.pattern, rule.source
+            )?,
+            Verdict::Kept(None) => writeln!(out, "    not ignored")?,
+        }
+    }
+
+    if !found {
+        writeln!(out, "  not under any of the shortcut_paths in [search]")?;
+    }
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOT: &str = "/start menu";
+
+    fn strings(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|pattern| pattern.to_string()).collect()
+    }
+
+    fn root_filter(ignore: &[&str], include: &[&str], exclude: &[&str]) -> RootFilter {
+        let search = config::Search {
+            ignore_paths: strings(ignore),
+            ..config::Search::default()
+        };
+        let root = config::ShortcutRoot {
+            path: ROOT.to_string(),
+            include: strings(include),
+            exclude: strings(exclude),
+            ..config::ShortcutRoot::default()
+        };
+        RootFilter::new(&search, &root).unwrap()
+    }
+
+    fn ignores(patterns: &[&str]) -> RootFilter {
+        root_filter(patterns, &[], &[])
+    }
+
+    // what `check` says about a path under ROOT
+    fn verdict(filter: &RootFilter, path: &str) -> String {
+        match filter.check(&Path::new(ROOT).join(path)) {
+            Verdict::Excluded(rule) => format!("excluded by {}", rule.pattern),
+            Verdict::NotIncluded => "not included".to_string(),
+            Verdict::Kept(Some(rule)) => format!("kept by {}", rule.pattern),
+            Verdict::Kept(None) => "kept".to_string(),
+        }
+    }
+
+    fn is_ignored(filter: &RootFilter, path: &str) -> bool {
+        !matches!(filter.check(&Path::new(ROOT).join(path)), Verdict::Kept(_))
+    }
+
+    #[test]
+    fn anchoring() {
+        let filter = ignores(&["Programs/Games/*", "Uninstall *.lnk"]);
+        assert!(is_ignored(&filter, "Programs/Games/Doom.lnk"));
+        assert!(is_ignored(&filter, "Programs/Games/id/Doom.lnk"));
+        assert!(!is_ignored(&filter, "Other/Programs/Games/Doom.lnk"));
+        assert!(is_ignored(&filter, "Uninstall Doom.lnk"));
+        assert!(is_ignored(&filter, "Programs/Doom/Uninstall Doom.lnk"));
+        assert!(!is_ignored(&filter, "Programs/Doom/Uninstall Doom.url"));
+
+        let filter = ignores(&["./Tools"]);
+        assert!(is_ignored(&filter, "Tools/Git.lnk"));
+        assert!(!is_ignored(&filter, "Programs/Tools/Git.lnk"));
+    }
+
+    #[test]
+    fn absolute_paths() {
+        let filter = ignores(&["/start menu/Programs/Startup"]);
+        assert!(is_ignored(&filter, "Programs/Startup/Updater.lnk"));
+        assert!(!is_ignored(&filter, "Programs/Updater.lnk"));
+
+        // a leading `/` is the filesystem root, not the shortcut root
+        let filter = ignores(&["/Programs"]);
+        assert!(!is_ignored(&filter, "Programs/Updater.lnk"));
+    }
+
+    #[test]
+    fn expanded_variables_are_literal() {
+        std::env::set_var("TISTOW_IGNORE_DIR", "/start menu/Programs/[x]*");
+        let filter = ignores(&["${TISTOW_IGNORE_DIR}/Games", "$TISTOW_IGNORE_DIR/*.url"]);
+        assert!(is_ignored(&filter, "Programs/[x]*/Games/Doom.lnk"));
+        assert!(!is_ignored(&filter, "Programs/x/Games/Doom.lnk"));
+        assert!(!is_ignored(&filter, "Programs/[x]abc/Games/Doom.lnk"));
+
+        // the pattern's own stars still are globs
+        assert!(is_ignored(&filter, "Programs/[x]*/Site.url"));
+        assert!(!is_ignored(&filter, "Programs/[x]*/Site.lnk"));
+    }
+
+    #[test]
+    fn stars() {
+        let filter = ignores(&["Programs/*.lnk"]);
+        assert!(is_ignored(&filter, "Programs/Doom.lnk"));
+        assert!(!is_ignored(&filter, "Programs/Games/Doom.lnk"));
+
+        let filter = ignores(&["Programs/**/Doom.lnk"]);
+        assert!(is_ignored(&filter, "Programs/Doom.lnk"));
+        assert!(is_ignored(&filter, "Programs/Games/id/Doom.lnk"));
+        assert!(!is_ignored(&filter, "Games/Doom.lnk"));
+    }
+
+    #[test]
+    fn trailing_slash_only_matches_folders() {
+        let filter = ignores(&["Games/"]);
+        assert!(is_ignored(&filter, "Games/Doom.lnk"));
+        assert!(is_ignored(&filter, "Programs/Games/Doom.lnk"));
+        assert!(!is_ignored(&filter, "Games"));
+        assert!(!is_ignored(&filter, "Programs/Games"));
+
+        let filter = ignores(&["Games"]);
+        assert!(is_ignored(&filter, "Games"));
+        assert!(is_ignored(&filter, "Games/Doom.lnk"));
+    }
+
+    #[test]
+    fn regexes() {
+        // matched against the full path, only anchored where the regex says
+        let filter = ignores(&[r"re:/steam/.*\.url$"]);
+        assert!(is_ignored(&filter, "Steam/Doom.url"));
+        assert!(is_ignored(&filter, "Programs/Steam/Doom.URL"));
+        assert!(!is_ignored(&filter, "Steam/Doom.lnk"));
+
+        let filter = ignores(&["re:^steam/"]);
+        assert!(!is_ignored(&filter, "Steam/Doom.url"));
+
+        let error = Rules::new(&strings(&["re:("]), "[search] ignore_paths")
+            .err()
+            .unwrap();
+        assert!(error
+            .to_string()
+            .starts_with("`re:(` in [search] ignore_paths: "));
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let filter = ignores(&["programs/GAMES/*", "uninstall*"]);
+        assert!(is_ignored(&filter, "Programs/Games/Doom.LNK"));
+        assert!(is_ignored(&filter, "Programs/UNINSTALL Doom.lnk"));
+
+        // the root itself too, windows paths aren't case sensitive
+        assert!(filter.contains(Path::new("/START MENU/Programs")));
+        assert!(filter.excludes(Path::new("/Start Menu/programs/games/doom.lnk")));
+        assert!(!filter.contains(Path::new("/start menus/Programs")));
+    }
+
+    #[test]
+    fn precedence() {
+        // the last matching pattern wins
+        let filter = ignores(&["Games/*", "!Games/Doom.lnk"]);
+        assert_eq!(
+            verdict(&filter, "Games/Doom.lnk"),
+            "kept by !Games/Doom.lnk"
+        );
+        assert_eq!(verdict(&filter, "Games/Quake.lnk"), "excluded by Games/*");
+        let filter = ignores(&["!Games/Doom.lnk", "Games/*"]);
+        assert_eq!(verdict(&filter, "Games/Doom.lnk"), "excluded by Games/*");
+
+        // an ignored folder isn't walked, so nothing in it can be un-ignored
+        let filter = ignores(&["Games", "!Games/Doom.lnk"]);
+        assert_eq!(verdict(&filter, "Games/Doom.lnk"), "excluded by Games");
+
+        // a root's own patterns come after ignore_paths
+        let filter = root_filter(&["Games/*"], &[], &["!Games/Doom.lnk"]);
+        assert_eq!(
+            verdict(&filter, "Games/Doom.lnk"),
+            "kept by !Games/Doom.lnk"
+        );
+        let filter = root_filter(&["!Games/Doom.lnk"], &[], &["Games/*"]);
+        assert_eq!(verdict(&filter, "Games/Doom.lnk"), "excluded by Games/*");
+
+        // excluding beats including
+        let filter = root_filter(&[], &["Games/*"], &["Games/Quake.lnk"]);
+        assert_eq!(verdict(&filter, "Games/Doom.lnk"), "kept");
+        assert_eq!(verdict(&filter, "Tools/Git.lnk"), "not included");
+        assert_eq!(
+            verdict(&filter, "Games/Quake.lnk"),
+            "excluded by Games/Quake.lnk"
+        );
+        // includes are only checked on shortcuts, not on the folders on the way down
+        assert!(!filter.excludes(Path::new("/start menu/Tools")));
+    }
+
+    #[test]
+    fn explain_ignore() {
+        // an absolute path on every platform, which explain leaves as it is
+        let root = std::env::temp_dir().join("start menu");
+        let programs = root.join("Programs");
+        let search = config::Search {
+            shortcut_paths: vec![
+                config::ShortcutPath::Path(root.display().to_string()),
+                config::ShortcutPath::Root(config::ShortcutRoot {
+                    path: programs.display().to_string(),
+                    exclude: strings(&["!Games/Doom.lnk"]),
+                    ..config::ShortcutRoot::default()
+                }),
+            ],
+            ignore_paths: strings(&["Games/"]),
+            ..config::Search::default()
+        };
+        let explain = |path: &Path| explain(&search, path).unwrap();
+
+        let doom = programs.join("Games").join("Doom.lnk");
+        assert_eq!(
+            explain(&doom),
+            format!(
+                "{}\n  under {}\n    ignored by `Games/` from [search] ignore_paths\n  \
+                 under {}\n    not ignored, `!Games/Doom.lnk` from excludes of {} \
+                 un-ignores it\n",
+                doom.display(),
+                root.display(),
+                programs.display(),
+                programs.display()
+            )
+        );
+
+        let doom = root.join("Doom.lnk");
+        assert_eq!(
+            explain(&doom),
+            format!(
+                "{}\n  under {}\n    not ignored\n",
+                doom.display(),
+                root.display()
+            )
+        );
+
+        let elsewhere = std::env::temp_dir().join("Applications").join("Doom.app");
+        assert_eq!(
+            explain(&elsewhere),
+            format!(
+                "{}\n  not under any of the shortcut_paths in [search]\n",
+                elsewhere.display()
+            )
+        );
+
+        // relative paths are taken from the current folder
+        let relative = explain(Path::new("Doom.lnk"));
+        let current = std::env::current_dir().unwrap().join("Doom.lnk");
+        assert!(relative.starts_with(&current.display().to_string()));
+    }
+}
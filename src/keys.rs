@@ -0,0 +1,141 @@
+// key bindings for the launcher window, configured in `[keys]` as chords like "ctrl+shift+enter"
+
+use egui::{InputState, Key, Modifiers};
+
+use crate::config;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Next,
+    Complete,
+    Previous,
+    PageDown,
+    PageUp,
+    First,
+    Last,
+    Run,
+    RunSecondary,
+    RunElevated,
+    CopyPath,
+    OpenFolder,
+    Close,
+    ClearQuery,
+    HistoryUp,
+    ToggleHidden,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Chord {
+    modifiers: Modifiers,
+    key: Key,
+}
+
+#[rustfmt::skip]
+const KEYS: [(&str, Key); 53] = [
+    ("down", Key::ArrowDown), ("left", Key::ArrowLeft), ("right", Key::ArrowRight), ("up", Key::ArrowUp),
+    ("escape", Key::Escape), ("esc", Key::Escape), ("tab", Key::Tab), ("backspace", Key::Backspace),
+    ("enter", Key::Enter), ("return", Key::Enter), ("space", Key::Space),
+    ("insert", Key::Insert), ("delete", Key::Delete), ("home", Key::Home), ("end", Key::End),
+    ("pageup", Key::PageUp), ("pagedown", Key::PageDown),
+    ("0", Key::Num0), ("1", Key::Num1), ("2", Key::Num2), ("3", Key::Num3), ("4", Key::Num4),
+    ("5", Key::Num5), ("6", Key::Num6), ("7", Key::Num7), ("8", Key::Num8), ("9", Key::Num9),
+    ("a", Key::A), ("b", Key::B), ("c", Key::C), ("d", Key::D), ("e", Key::E), ("f", Key::F),
+    ("g", Key::G), ("h", Key::H), ("i", Key::I), ("j", Key::J), ("k", Key::K), ("l", Key::L),
+    ("m", Key::M), ("n", Key::N), ("o", Key::O), ("p", Key::P), ("q", Key::Q), ("r", Key::R),
+    ("s", Key::S), ("t", Key::T), ("u", Key::U), ("v", Key::V), ("w", Key::W), ("x", Key::X),
+    ("y", Key::Y), ("z", Key::Z),
+];
+
+fn parse_key(name: &str) -> Option<Key> {
+    KEYS.iter()
+        .find(|(key_name, _)| *key_name == name)
+        .map(|(_, key)| *key)
+}
+
+fn parse_chord(chord: &str) -> anyhow::Result<Chord> {
+    let lowercase = chord.to_lowercase();
+    let mut parts: Vec<&str> = lowercase.split('+').map(str::trim).collect();
+
+    let key = parts.pop().unwrap_or_default();
+    let key =
+        parse_key(key).ok_or_else(|| anyhow::anyhow!("unknown key `{}` in `{}`", key, chord))?;
+
+    let mut modifiers = Modifiers::NONE;
+    for part in parts {
+        match part {
+            "ctrl" | "control" => modifiers.ctrl = true,
+            "shift" => modifiers.shift = true,
+            "alt" | "option" => modifiers.alt = true,
+            "cmd" | "command" => modifiers.mac_cmd = true,
+            _ => anyhow::bail!("unknown modifier `{}` in `{}`", part, chord),
+        }
+    }
+
+    Ok(Chord { modifiers, key })
+}
+
+pub struct Bindings {
+    chords: Vec<(Chord, Command)>,
+}
+
+impl Bindings {
+    pub fn new(keys: &config::Keys) -> anyhow::Result<Self> {
+        let commands = [
+            (Command::Next, &keys.next, "next"),
+            (Command::Complete, &keys.complete, "complete"),
+            (Command::Previous, &keys.previous, "previous"),
+            (Command::PageDown, &keys.page_down, "page-down"),
+            (Command::PageUp, &keys.page_up, "page-up"),
+            (Command::First, &keys.first, "first"),
+            (Command::Last, &keys.last, "last"),
+            (Command::Run, &keys.run, "run"),
+            (Command::RunSecondary, &keys.run_secondary, "run-secondary"),
+            (Command::RunElevated, &keys.run_elevated, "run-elevated"),
+            (Command::CopyPath, &keys.copy_path, "copy-path"),
+            (Command::OpenFolder, &keys.open_folder, "open-folder"),
+            (Command::Close, &keys.close, "close"),
+            (Command::ClearQuery, &keys.clear_query, "clear-query"),
+            (Command::HistoryUp, &keys.history_up, "history-up"),
+            (Command::ToggleHidden, &keys.toggle_hidden, "toggle-hidden"),
+        ];
+
+        let mut chords: Vec<(Chord, Command, &str, &str)> = Vec::new();
+        for (command, bound, command_name) in commands {
+            for text in bound {
+                let chord = parse_chord(text)?;
+
+                if let Some((_, _, other_text, other_name)) =
+                    chords.iter().find(|(other, ..)| *other == chord)
+                {
+                    anyhow::bail!(
+                        "`{}` ({}) and `{}` ({}) are the same key",
+                        other_text,
+                        other_name,
+                        text,
+                        command_name
+                    );
+                }
+
+                chords.push((chord, command, text.as_str(), command_name));
+            }
+        }
+
+        Ok(Self {
+            chords: chords
+                .into_iter()
+                .map(|(chord, command, ..)| (chord, command))
+                .collect(),
+        })
+    }
+
+    /// Returns the commands triggered this frame, taking their key presses out of
+    /// `input` so the text field doesn't also act on them.
+    pub fn pressed(&self, input: &mut InputState) -> Vec<Command> {
+        // modifiers have to match exactly, so ctrl+enter doesn't fire on ctrl+shift+enter
+        self.chords
+            .iter()
+            .filter(|(chord, _)| input.consume_key(chord.modifiers, chord.key))
+            .map(|(_, command)| *command)
+            .collect()
+    }
+}
@@ -0,0 +1,57 @@
+// how often and when each app or file was last launched from tistow, for the preview
+// panel. kept in the data dir rather than the cache dir, since it can't be rebuilt
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+pub struct Launch {
+    pub count: u32,
+    // unix timestamp
+    pub last: i64,
+}
+
+// keyed by path
+#[derive(Default)]
+pub struct Launches(HashMap<String, Launch>);
+
+fn launches_path() -> PathBuf {
+    let project_dir = ProjectDirs::from("", "", "tistow").expect("couldn't get project dir");
+    project_dir.data_dir().join("launches.json")
+}
+
+impl Launches {
+    pub fn load() -> Self {
+        let launches = std::fs::read_to_string(launches_path())
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok());
+        Self(launches.unwrap_or_default())
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        let path = launches_path();
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, serde_json::to_string(&self.0)?)?;
+        Ok(())
+    }
+
+    pub fn get(&self, path: &Path) -> Option<Launch> {
+        self.0.get(path.to_string_lossy().as_ref()).copied()
+    }
+
+    pub fn record(&mut self, path: &Path) {
+        let launch = self
+            .0
+            .entry(path.to_string_lossy().into_owned())
+            .or_default();
+        launch.count += 1;
+        launch.last = chrono::Local::now().timestamp();
+
+        if let Err(e) = self.save() {
+            println!("couldn't save launches: {}", e);
+        }
+    }
+}
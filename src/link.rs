@@ -0,0 +1,444 @@
+// reads windows shortcuts: .lnk files (the binary Shell Link format, [MS-SHLLINK]) and
+// .url files (a small ini file). it's plain byte parsing, so it builds and runs anywhere
+
+use std::path::Path;
+
+#[derive(Clone, Debug, Default)]
+pub struct Link {
+    // a path for .lnk files, a url for .url files. `None` when the shortcut doesn't say,
+    // e.g. for msi "advertised" shortcuts
+    pub target: Option<String>,
+    pub arguments: Option<String>,
+    pub working_dir: Option<String>,
+    pub description: Option<String>,
+    // file and index, like "C:\Windows\System32\shell32.dll" and 3
+    pub icon: Option<(String, i32)>,
+}
+
+impl Link {
+    /// Whether the target is a path that doesn't exist anymore, e.g. after an uninstall
+    /// that left its shortcut behind.
+    pub fn is_broken(&self) -> bool {
+        match &self.target {
+            Some(target) if !target.contains("://") => !Path::new(target).exists(),
+            _ => false,
+        }
+    }
+}
+
+/// Reads a .lnk or .url file, going by its extension. `None` for anything else.
+pub fn read(path: &Path) -> anyhow::Result<Option<Link>> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    match extension.as_str() {
+        "lnk" => {
+            let mut link = parse_lnk(&std::fs::read(path)?)?;
+
+            // a relative target is relative to the shortcut itself
+            if let (Some(target), Some(folder)) = (&link.target, path.parent()) {
+                if !is_absolute(target) && !target.contains("://") {
+                    link.target = Some(folder.join(target).to_string_lossy().into_owned());
+                }
+            }
+
+            Ok(Some(link))
+        }
+        "url" => Ok(Some(parse_url(&String::from_utf8_lossy(&std::fs::read(
+            path,
+        )?)))),
+        _ => Ok(None),
+    }
+}
+
+// windows style, `C:\...` or `\\server\...`
+fn is_absolute(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.starts_with("\\\\") || (bytes.len() >= 2 && bytes[1] == b':')
+}
+
+// %VAR% style, unknown variables are left alone
+fn expand_env(text: &str) -> String {
+    let mut expanded = String::new();
+    let mut rest = text;
+
+    while let Some(start) = rest.find('%') {
+        let Some(len) = rest[start + 1..].find('%') else {
+            break;
+        };
+        let name = &rest[start + 1..start + 1 + len];
+
+        expanded.push_str(&rest[..start]);
+        match std::env::var(name) {
+            Ok(value) if !name.is_empty() => expanded.push_str(&value),
+            _ => expanded.push_str(&rest[start..start + len + 2]),
+        }
+        rest = &rest[start + len + 2..];
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+fn non_empty(text: String) -> Option<String> {
+    Some(text).filter(|text| !text.is_empty())
+}
+
+// little-endian reads that fail instead of panicking on a truncated file
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&self, offset: usize, len: usize) -> anyhow::Result<&'a [u8]> {
+        self.data
+            .get(offset..offset + len)
+            .ok_or_else(|| anyhow::anyhow!("shortcut ends early at {}", offset))
+    }
+
+    fn u16(&self, offset: usize) -> anyhow::Result<u16> {
+        Ok(u16::from_le_bytes(self.bytes(offset, 2)?.try_into()?))
+    }
+
+    fn u32(&self, offset: usize) -> anyhow::Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(offset, 4)?.try_into()?))
+    }
+
+    // up to the first nul, in the system codepage. only ascii is decoded properly,
+    // which is why the unicode copies are preferred when there are any
+    fn ansi(&self, offset: usize) -> String {
+        let data = self.data.get(offset..).unwrap_or_default();
+        let len = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+        String::from_utf8_lossy(&data[..len]).into_owned()
+    }
+
+    fn unicode(&self, offset: usize) -> String {
+        let units: Vec<u16> = self
+            .data
+            .get(offset..)
+            .unwrap_or_default()
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .take_while(|&unit| unit != 0)
+            .collect();
+        String::from_utf16_lossy(&units)
+    }
+}
+
+const HEADER_SIZE: u32 = 0x4c;
+const LINK_CLSID: [u8; 16] = [
+    0x01, 0x14, 0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x46,
+];
+
+const HAS_LINK_TARGET_ID_LIST: u32 = 0x1;
+const HAS_LINK_INFO: u32 = 0x2;
+const HAS_NAME: u32 = 0x4;
+const HAS_RELATIVE_PATH: u32 = 0x8;
+const HAS_WORKING_DIR: u32 = 0x10;
+const HAS_ARGUMENTS: u32 = 0x20;
+const HAS_ICON_LOCATION: u32 = 0x40;
+const IS_UNICODE: u32 = 0x80;
+
+const VOLUME_ID_AND_LOCAL_BASE_PATH: u32 = 0x1;
+const COMMON_NETWORK_RELATIVE_LINK: u32 = 0x2;
+
+const ENVIRONMENT_BLOCK: u32 = 0xa000_0001;
+const ICON_ENVIRONMENT_BLOCK: u32 = 0xa000_0007;
+
+// the target path from the LinkInfo structure, a local path or a network share
+fn link_info_path(reader: &Reader, start: usize) -> anyhow::Result<Option<String>> {
+    let header_size = reader.u32(start + 4)?;
+    let flags = reader.u32(start + 8)?;
+    let local_base_offset = reader.u32(start + 16)? as usize;
+    let network_offset = reader.u32(start + 20)? as usize;
+    let suffix_offset = reader.u32(start + 24)? as usize;
+
+    // newer shortcuts also carry unicode copies of the strings
+    let unicode = header_size >= 0x24;
+    let suffix = if unicode {
+        reader.unicode(start + reader.u32(start + 32)? as usize)
+    } else {
+        reader.ansi(start + suffix_offset)
+    };
+
+    let base = if flags & VOLUME_ID_AND_LOCAL_BASE_PATH != 0 {
+        if unicode {
+            reader.unicode(start + reader.u32(start + 28)? as usize)
+        } else {
+            reader.ansi(start + local_base_offset)
+        }
+    } else if flags & COMMON_NETWORK_RELATIVE_LINK != 0 {
+        let network = start + network_offset;
+        let share = reader.ansi(network + reader.u32(network + 8)? as usize);
+        if suffix.is_empty() {
+            share
+        } else {
+            format!("{}\\", share.trim_end_matches('\\'))
+        }
+    } else {
+        return Ok(None);
+    };
+
+    Ok(non_empty(base + &suffix))
+}
+
+/// Parses the contents of a .lnk file.
+pub fn parse_lnk(data: &[u8]) -> anyhow::Result<Link> {
+    let reader = Reader { data };
+
+    anyhow::ensure!(
+        reader.u32(0)? == HEADER_SIZE && reader.bytes(4, 16)? == LINK_CLSID,
+        "not a shell link"
+    );
+
+    let flags = reader.u32(0x14)?;
+    let icon_index = reader.u32(0x38)? as i32;
+    let mut offset = HEADER_SIZE as usize;
+
+    if flags & HAS_LINK_TARGET_ID_LIST != 0 {
+        offset += 2 + reader.u16(offset)? as usize;
+    }
+
+    let mut target = None;
+    if flags & HAS_LINK_INFO != 0 {
+        target = link_info_path(&reader, offset)?;
+        offset += reader.u32(offset)? as usize;
+    }
+
+    // the string data comes in this order, each only if its flag is set
+    let mut strings = [
+        (HAS_NAME, None),
+        (HAS_RELATIVE_PATH, None),
+        (HAS_WORKING_DIR, None),
+        (HAS_ARGUMENTS, None),
+        (HAS_ICON_LOCATION, None),
+    ];
+    for (flag, value) in &mut strings {
+        if flags & *flag == 0 {
+            continue;
+        }
+
+        let count = reader.u16(offset)? as usize;
+        offset += 2;
+
+        let text = if flags & IS_UNICODE != 0 {
+            let units: Vec<u16> = reader
+                .bytes(offset, count * 2)?
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .collect();
+            offset += count * 2;
+            String::from_utf16_lossy(&units)
+        } else {
+            let bytes = reader.bytes(offset, count)?;
+            offset += count;
+            String::from_utf8_lossy(bytes).into_owned()
+        };
+        *value = non_empty(text);
+    }
+    let [(_, description), (_, relative_path), (_, working_dir), (_, arguments), (_, icon)] =
+        strings;
+
+    // extra data blocks, until one that's too small to be a block
+    let mut env_target = None;
+    let mut env_icon = None;
+    while let Ok(size) = reader.u32(offset) {
+        if size < 8 {
+            break;
+        }
+
+        let signature = reader.u32(offset + 4)?;
+        if signature == ENVIRONMENT_BLOCK || signature == ICON_ENVIRONMENT_BLOCK {
+            // 260 bytes of ansi, then 520 of unicode
+            let mut path = reader.unicode(offset + 8 + 260);
+            if path.is_empty() {
+                path = reader.ansi(offset + 8);
+            }
+
+            if signature == ENVIRONMENT_BLOCK {
+                env_target = non_empty(path);
+            } else {
+                env_icon = non_empty(path);
+            }
+        }
+
+        offset += size as usize;
+    }
+
+    // the environment block is what windows itself goes by, e.g. %ProgramFiles% that
+    // resolves differently on 32 and 64 bit
+    let target = env_target.or(target).or(relative_path);
+
+    Ok(Link {
+        target: target.map(|target| expand_env(&target)),
+        arguments,
+        working_dir: working_dir.map(|dir| expand_env(&dir)),
+        description,
+        icon: env_icon
+            .or(icon)
+            .map(|icon| (expand_env(&icon), icon_index)),
+    })
+}
+
+/// Parses the contents of a .url file, the `[InternetShortcut]` section of it.
+pub fn parse_url(text: &str) -> Link {
+    let mut link = Link::default();
+    let mut icon_file = None;
+    let mut icon_index = 0;
+    let mut in_section = false;
+
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_section = line.eq_ignore_ascii_case("[InternetShortcut]");
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim().to_string();
+
+        match key.trim().to_lowercase().as_str() {
+            "url" => link.target = non_empty(value),
+            "workingdirectory" => link.working_dir = non_empty(value),
+            "iconfile" => icon_file = non_empty(value),
+            "iconindex" => icon_index = value.parse().unwrap_or(0),
+            _ => {}
+        }
+    }
+
+    link.icon = icon_file.map(|file| (file, icon_index));
+    link
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    // made by tests/fixtures/links/generate.py
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/links")
+            .join(name)
+    }
+
+    fn read_fixture(name: &str) -> Link {
+        read(&fixture(name)).unwrap().unwrap()
+    }
+
+    fn icon(file: &str, index: i32) -> Option<(String, i32)> {
+        Some((file.to_string(), index))
+    }
+
+    #[test]
+    fn link_info_with_unicode_paths() {
+        let link = read_fixture("local.lnk");
+        // the ansi copy has a ? in it
+        assert_eq!(
+            link.target.as_deref(),
+            Some("C:\\Program Files\\Café\\app.exe")
+        );
+        assert_eq!(link.arguments.as_deref(), Some("--open \"two words\""));
+        assert_eq!(link.working_dir.as_deref(), Some("C:\\Program Files\\Café"));
+        assert_eq!(link.description.as_deref(), Some("Café ☕"));
+        // unknown variables are left alone
+        assert_eq!(link.icon, icon("%TISTOW_NOT_SET%\\icons.dll", 3));
+        assert!(link.is_broken());
+    }
+
+    #[test]
+    fn environment_block() {
+        std::env::set_var("TISTOW_FIXTURE_DIR", "D:\\tools");
+        let link = read_fixture("environment.lnk");
+        assert_eq!(link.target.as_deref(), Some("D:\\tools\\tool.exe"));
+        assert_eq!(link.arguments.as_deref(), Some("/quiet"));
+        assert_eq!(link.working_dir, None);
+        assert_eq!(link.description, None);
+        // from the ansi half of the icon block
+        assert_eq!(link.icon, icon("D:\\tools\\tool.ico", 0));
+
+        assert_eq!(expand_env("%TISTOW_FIXTURE_DIR%%%%"), "D:\\tools%%%");
+        assert_eq!(expand_env("50% off, 100%"), "50% off, 100%");
+    }
+
+    #[test]
+    fn advertised_shortcut() {
+        let link = read_fixture("advertised.lnk");
+        assert_eq!(link.target, None);
+        assert_eq!(link.description.as_deref(), Some("Advertised app"));
+        assert_eq!(
+            link.icon,
+            icon("C:\\Windows\\Installer\\{GUID}\\app.ico", 0)
+        );
+        // nothing to check, so it's not marked as broken
+        assert!(!link.is_broken());
+    }
+
+    #[test]
+    fn network_share() {
+        let link = read_fixture("network.lnk");
+        assert_eq!(
+            link.target.as_deref(),
+            Some("\\\\server\\share\\docs\\report.docx")
+        );
+        assert_eq!(link.arguments.as_deref(), Some("/readonly"));
+        assert_eq!(link.working_dir.as_deref(), Some("\\\\server\\share\\docs"));
+        assert_eq!(link.icon, None);
+    }
+
+    #[test]
+    fn relative_target() {
+        let link = read_fixture("relative.lnk");
+        let target = fixture("notes.txt").to_string_lossy().into_owned();
+        assert_eq!(link.target, Some(target));
+        assert!(!link.is_broken());
+    }
+
+    #[test]
+    fn truncated_and_invalid_files() {
+        assert!(read(&fixture("truncated.lnk")).is_err());
+        assert!(read(&fixture("notes.txt")).unwrap().is_none());
+        assert!(
+            parse_lnk(b"not a shortcut at all, but long enough to have a header .......").is_err()
+        );
+
+        // no cut-off point panics
+        for name in ["local.lnk", "environment.lnk", "network.lnk"] {
+            let data = std::fs::read(fixture(name)).unwrap();
+            for len in 0..data.len() {
+                parse_lnk(&data[..len]).ok();
+            }
+        }
+    }
+
+    #[test]
+    fn url_files() {
+        // only [InternetShortcut] counts, in any case, and so do its keys
+        let link = read_fixture("site.url");
+        assert_eq!(
+            link.target.as_deref(),
+            Some("https://example.com/docs?a=1&b=2")
+        );
+        assert_eq!(link.working_dir.as_deref(), Some("C:\\Users\\me"));
+        assert_eq!(link.arguments, None);
+        assert_eq!(
+            link.icon,
+            icon("C:\\Program Files\\Browser\\browser.exe", 2)
+        );
+        assert!(!link.is_broken());
+
+        let link = read_fixture("steam.url");
+        assert_eq!(link.target.as_deref(), Some("steam://rungameid/570"));
+        assert_eq!(
+            link.icon,
+            icon("C:\\Program Files (x86)\\Steam\\steam\\games\\dota.ico", 0)
+        );
+    }
+}
@@ -3,12 +3,25 @@
 use std::fs;
 
 use eframe::egui;
-use egui::{FontData, FontDefinitions, FontFamily, Pos2, Vec2};
+use egui::{FontData, FontDefinitions, FontFamily};
 
 mod app;
+mod bundle;
+mod calc;
 mod config;
+mod currency;
+mod date;
+mod files;
+mod icons;
+mod ignore;
+mod keys;
+mod launches;
+mod link;
+mod preview;
 mod search;
+mod shell;
 mod util;
+mod window;
 
 #[cfg(target_os = "windows")]
 fn fix_stdout() {
@@ -24,23 +37,33 @@ fn main() {
     fix_stdout();
 
     let config = config::get_config();
+
+    // `tistow explain-ignore <path>` shows which ignore pattern hides a shortcut
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("explain-ignore") {
+        match args.get(2) {
+            Some(path) => match ignore::explain(&config.search, std::path::Path::new(path)) {
+                Ok(explanation) => print!("{}", explanation),
+                Err(e) => println!("couldn't explain {}: {}", path, e),
+            },
+            None => println!("usage: tistow explain-ignore <path>"),
+        }
+        return;
+    }
+
     println!("{:#?}", config);
 
+    let monitor = window::active_monitor(config.window.monitor);
+
     eframe::run_native(
-        "tistow",
+        window::TITLE,
         eframe::NativeOptions {
             transparent: true,
             resizable: false,
             always_on_top: true,
             decorated: false,
-            initial_window_size: Some(Vec2 {
-                x: config.window.width as f32,
-                y: config.window.height as f32,
-            }),
-            initial_window_pos: Some(Pos2 {
-                x: config.window.x as f32,
-                y: config.window.y as f32,
-            }),
+            initial_window_size: Some(window::size(&config.window, monitor.as_ref(), None)),
+            initial_window_pos: Some(window::position(&config.window, monitor.as_ref())),
             ..eframe::NativeOptions::default()
         },
         Box::new(|cc| {
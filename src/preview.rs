@@ -0,0 +1,474 @@
+// the panel next to the results with more about the selected one, `preview = true` in
+// [style]. search.rs decides what goes in it, this draws it
+
+use std::cell::RefCell;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+use arboard::Clipboard;
+use egui::text::LayoutJob;
+use egui::{ColorImage, RichText, TextFormat, TextStyle, TextureHandle};
+
+use crate::calc::Output;
+use crate::launches::Launch;
+use crate::search::{Search, SearchResult};
+
+// enough of a text file to tell what it is
+const TEXT_BYTES: u64 = 8 * 1024;
+const TEXT_LINES: usize = 40;
+// decoding a huge image takes long and needs a lot of memory, skip those
+const MAX_IMAGE_BYTES: u64 = 16 * 1024 * 1024;
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "bmp", "ico"];
+
+pub enum Body {
+    // the start of a text file
+    Text(String),
+    Image(PathBuf),
+    // other ways to write a calculator result, each one copied on click
+    Alternatives(Vec<Output>),
+    // from a lua entry's `preview`
+    Markdown(String),
+}
+
+pub struct Preview {
+    pub title: String,
+    pub details: Vec<(&'static str, String)>,
+    pub body: Option<Body>,
+}
+
+fn format_time(time: chrono::DateTime<chrono::Local>) -> String {
+    time.format("%Y-%m-%d %H:%M").to_string()
+}
+
+fn format_size(bytes: u64) -> String {
+    let units = ["bytes", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", bytes, units[0])
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
+
+pub fn launch_details(launch: Option<Launch>) -> Vec<(&'static str, String)> {
+    let launch = launch.unwrap_or_default();
+    let count = match launch.count {
+        1 => "once".to_string(),
+        count => format!("{} times", count),
+    };
+    let mut details = vec![("launched", count)];
+
+    let last = chrono::TimeZone::timestamp_opt(&chrono::Local, launch.last, 0).single();
+    if let Some(last) = last.filter(|_| launch.count > 0) {
+        details.push(("last launched", format_time(last)));
+    }
+
+    details
+}
+
+pub fn file_details(path: &Path) -> Vec<(&'static str, String)> {
+    let mut details = vec![("path", path.display().to_string())];
+
+    if let Ok(metadata) = std::fs::metadata(path) {
+        if metadata.is_file() {
+            details.push(("size", format_size(metadata.len())));
+        }
+        if let Ok(modified) = metadata.modified() {
+            details.push(("modified", format_time(modified.into())));
+        }
+    }
+
+    details
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// An image, or the first lines of anything that looks like text.
+pub fn file_body(path: &Path) -> Option<Body> {
+    if !path.is_file() {
+        return None;
+    }
+    if is_image(path) {
+        return Some(Body::Image(path.to_path_buf()));
+    }
+
+    let mut data = Vec::new();
+    std::fs::File::open(path)
+        .ok()?
+        .take(TEXT_BYTES)
+        .read_to_end(&mut data)
+        .ok()?;
+
+    // nul bytes don't show up in text files
+    if data.contains(&0) {
+        return None;
+    }
+
+    let text = String::from_utf8_lossy(&data);
+    let lines: Vec<&str> = text.lines().take(TEXT_LINES).collect();
+    Some(Body::Text(lines.join("\n")))
+}
+
+fn load_image(path: &Path) -> anyhow::Result<ColorImage> {
+    let size = std::fs::metadata(path)?.len();
+    anyhow::ensure!(size <= MAX_IMAGE_BYTES, "image is too big to preview");
+
+    // the panel is never wider than this, no need to keep every pixel of a photo
+    let image = image::open(path)?.thumbnail(512, 512).to_rgba8();
+    Ok(ColorImage::from_rgba_unmultiplied(
+        [image.width() as usize, image.height() as usize],
+        image.as_raw(),
+    ))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Span {
+    Plain,
+    Code,
+    Bold,
+}
+
+// `code` and **bold** spans in a line of markdown. they don't nest, markers inside a
+// span are shown as they are, and so is a marker that's never closed
+fn inline_spans(text: &str) -> Vec<(Span, &str)> {
+    let mut spans = Vec::new();
+    // where the plain text that isn't in `spans` yet starts
+    let mut plain = 0;
+    let mut from = 0;
+
+    loop {
+        let code = text[from..].find('`');
+        let bold = text[from..].find("**");
+        let (start, marker, span) = match (code, bold) {
+            (Some(code), Some(bold)) if bold < code => (bold, "**", Span::Bold),
+            (Some(code), _) => (code, "`", Span::Code),
+            (None, Some(bold)) => (bold, "**", Span::Bold),
+            (None, None) => break,
+        };
+
+        let inner = from + start + marker.len();
+        let Some(end) = text[inner..].find(marker) else {
+            from = inner;
+            continue;
+        };
+
+        if plain < from + start {
+            spans.push((Span::Plain, &text[plain..from + start]));
+        }
+        spans.push((span, &text[inner..inner + end]));
+        from = inner + end + marker.len();
+        plain = from;
+    }
+
+    if plain < text.len() {
+        spans.push((Span::Plain, &text[plain..]));
+    }
+
+    spans
+}
+
+fn markdown_line(ui: &egui::Ui, prefix: &str, text: &str) -> LayoutJob {
+    let body = TextStyle::Body.resolve(ui.style());
+    let monospace = TextStyle::Monospace.resolve(ui.style());
+    let color = ui.visuals().text_color();
+    let strong = ui.visuals().strong_text_color();
+
+    let mut job = LayoutJob::default();
+    job.append(prefix, 0.0, TextFormat::simple(body.clone(), color));
+    for (span, text) in inline_spans(text) {
+        let format = match span {
+            Span::Plain => TextFormat::simple(body.clone(), color),
+            Span::Code => TextFormat::simple(monospace.clone(), color),
+            Span::Bold => TextFormat::simple(body.clone(), strong),
+        };
+        job.append(text, 0.0, format);
+    }
+
+    job
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Block<'a> {
+    Code(&'a str),
+    Gap,
+    Heading(&'a str),
+    Item(&'a str),
+    Line(&'a str),
+}
+
+// headings, lists, code blocks and the inline bits above, which is about all a lua
+// entry needs to explain itself. a code block that's never closed runs to the end
+fn markdown_blocks(text: &str) -> Vec<Block<'_>> {
+    let mut in_code = false;
+    let mut blocks = Vec::new();
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+
+        blocks.push(if in_code {
+            Block::Code(line)
+        } else if trimmed.is_empty() {
+            Block::Gap
+        } else if trimmed.starts_with('#') {
+            Block::Heading(trimmed.trim_start_matches('#').trim())
+        } else if let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+        {
+            Block::Item(item)
+        } else {
+            Block::Line(trimmed)
+        });
+    }
+
+    blocks
+}
+
+fn markdown(ui: &mut egui::Ui, text: &str) {
+    for block in markdown_blocks(text) {
+        match block {
+            Block::Code(line) => {
+                ui.label(RichText::new(line).monospace());
+            }
+            Block::Gap => ui.add_space(4.0),
+            Block::Heading(heading) => {
+                ui.label(RichText::new(heading).heading());
+            }
+            Block::Item(item) => {
+                ui.label(markdown_line(ui, "• ", item));
+            }
+            Block::Line(line) => {
+                ui.label(markdown_line(ui, "", line));
+            }
+        }
+    }
+}
+
+enum PreviewImage {
+    Loading,
+    Loaded(TextureHandle),
+    // already printed why
+    Failed,
+}
+
+pub struct Previewer {
+    // built again when the selection changes, not every frame
+    current: RefCell<Option<(String, Option<Preview>)>>,
+    // decoded on another thread, photos can take a while
+    image: RefCell<Option<(PathBuf, PreviewImage)>>,
+    requests_tx: mpsc::Sender<PathBuf>,
+    loaded_rx: mpsc::Receiver<(PathBuf, Option<ColorImage>)>,
+}
+
+impl Previewer {
+    pub fn new(ctx: egui::Context) -> Self {
+        let (requests_tx, requests_rx) = mpsc::channel::<PathBuf>();
+        let (loaded_tx, loaded_rx) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            while let Ok(path) = requests_rx.recv() {
+                // skip the ones the selection already moved past
+                let path = requests_rx.try_iter().last().unwrap_or(path);
+                let image = load_image(&path)
+                    .map_err(|e| println!("couldn't preview {}: {}", path.display(), e))
+                    .ok();
+
+                if loaded_tx.send((path, image)).is_err() {
+                    break;
+                }
+                ctx.request_repaint();
+            }
+        });
+
+        Self {
+            current: RefCell::new(None),
+            image: RefCell::new(None),
+            requests_tx,
+            loaded_rx,
+        }
+    }
+
+    fn draw_image(&self, ui: &mut egui::Ui, path: &Path) {
+        let mut image = self.image.borrow_mut();
+        if image.as_ref().map(|(shown, _)| shown.as_path()) != Some(path) {
+            self.requests_tx.send(path.to_path_buf()).ok();
+            *image = Some((path.to_path_buf(), PreviewImage::Loading));
+        }
+
+        for (loaded, color_image) in self.loaded_rx.try_iter() {
+            if loaded != path {
+                continue;
+            }
+            let texture = color_image.map(|color_image| {
+                ui.ctx()
+                    .load_texture(path.display().to_string(), color_image)
+            });
+            *image = Some((
+                loaded,
+                texture.map_or(PreviewImage::Failed, PreviewImage::Loaded),
+            ));
+        }
+
+        match image.as_ref().map(|(_, image)| image) {
+            Some(PreviewImage::Loading) => {
+                ui.label(RichText::new("loading image...").weak());
+            }
+            Some(PreviewImage::Loaded(texture)) => {
+                let size = texture.size_vec2();
+                let scale = (ui.available_width() / size.x).min(1.0);
+                ui.image(texture.id(), size * scale);
+            }
+            Some(PreviewImage::Failed) | None => {}
+        }
+    }
+
+    fn draw_body(&self, ui: &mut egui::Ui, body: &Body) {
+        match body {
+            Body::Text(text) => {
+                ui.label(RichText::new(text).monospace().small());
+            }
+            Body::Image(path) => self.draw_image(ui, path),
+            Body::Alternatives(outputs) => {
+                for output in outputs {
+                    let label = ui
+                        .selectable_label(false, &output.display)
+                        .on_hover_text("click to copy");
+                    if label.clicked() {
+                        if let Err(e) = Clipboard::new()
+                            .and_then(|mut clipboard| clipboard.set_text(output.value.clone()))
+                        {
+                            println!("couldn't copy to clipboard: {}", e);
+                        }
+                    }
+                }
+            }
+            Body::Markdown(text) => markdown(ui, text),
+        }
+    }
+
+    pub fn show(&self, ui: &mut egui::Ui, search: &Search, result: Option<&SearchResult>) {
+        let key = result.map(|result| {
+            format!(
+                "{}\0{}",
+                result.text,
+                result.subtitle.as_deref().unwrap_or_default()
+            )
+        });
+
+        let mut current = self.current.borrow_mut();
+        if current.as_ref().map(|(key, _)| key) != key.as_ref() {
+            *current = key.map(|key| (key, result.and_then(|result| search.preview(result))));
+        }
+
+        let Some((_, Some(preview))) = current.as_ref() else {
+            ui.label(RichText::new("nothing to preview").weak());
+            return;
+        };
+
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                ui.label(RichText::new(&preview.title).strong());
+
+                egui::Grid::new("preview_details")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        for (label, value) in &preview.details {
+                            ui.label(RichText::new(*label).weak());
+                            ui.add(egui::Label::new(value).wrap(true));
+                            ui.end_row();
+                        }
+                    });
+
+                if let Some(body) = &preview.body {
+                    ui.separator();
+                    self.draw_body(ui, body);
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inline_markdown() {
+        use Span::*;
+
+        assert_eq!(
+            inline_spans("run `tistow` **now**"),
+            [
+                (Plain, "run "),
+                (Code, "tistow"),
+                (Plain, " "),
+                (Bold, "now")
+            ]
+        );
+        assert_eq!(inline_spans("plain"), [(Plain, "plain")]);
+        assert_eq!(inline_spans(""), []);
+
+        // unclosed markers are just text, and don't hide the spans after them
+        assert_eq!(inline_spans("a `b"), [(Plain, "a `b")]);
+        assert_eq!(inline_spans("**a"), [(Plain, "**a")]);
+        assert_eq!(
+            inline_spans("2 ** 3 is `8`"),
+            [(Plain, "2 ** 3 is "), (Code, "8")]
+        );
+        assert_eq!(
+            inline_spans("a ` and **b**"),
+            [(Plain, "a ` and "), (Bold, "b")]
+        );
+
+        // no nesting, the inner markers are shown as they are
+        assert_eq!(
+            inline_spans("**see `x`** then"),
+            [(Bold, "see `x`"), (Plain, " then")]
+        );
+        assert_eq!(inline_spans("`a ** b`"), [(Code, "a ** b")]);
+        assert_eq!(
+            inline_spans("**a** **b**"),
+            [(Bold, "a"), (Plain, " "), (Bold, "b")]
+        );
+    }
+
+    #[test]
+    fn markdown_block_kinds() {
+        use Block::*;
+
+        let text =
+            "## Title\n\n- one\n* two\n  text\n```\n# not a heading\n  - nor an item\n```\nafter";
+        assert_eq!(
+            markdown_blocks(text),
+            [
+                Heading("Title"),
+                Gap,
+                Item("one"),
+                Item("two"),
+                Line("text"),
+                Code("# not a heading"),
+                Code("  - nor an item"),
+                Line("after"),
+            ]
+        );
+
+        // an unclosed code block runs to the end
+        assert_eq!(markdown_blocks("```\n**a**\n"), [Code("**a**")]);
+        assert_eq!(markdown_blocks("-not an item"), [Line("-not an item")]);
+    }
+}
@@ -1,39 +1,149 @@
+use crate::{
+    calc,
+    config::Config,
+    date, files,
+    icons::IconSource,
+    launches::Launches,
+    preview::{self, Body, Preview},
+    shell,
+    util::{self, Shortcut},
+};
 use figment::value::Map;
 use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
-use std::path::PathBuf;
+use std::{
+    cell::{Cell, RefCell},
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+const MAX_QUERY_HISTORY: usize = 50;
+// reading a huge directory into the list every keystroke gets slow
+const MAX_PATH_RESULTS: usize = 200;
+const MAX_FILE_RESULTS: usize = 50;
 
 #[derive(Clone)]
 pub enum SearchMode {
     Search,
     Calculator,
+    Shell,
+    Path,
+    Files,
 }
 
 #[derive(Clone)]
 pub struct SearchResult {
     pub mode: SearchMode,
     pub text: String,
-    pub action: Option<ResultAction>,
+    // shown dimmed under the text, e.g. what a shortcut points at
+    pub subtitle: Option<String>,
+    // drawn next to the text once it's loaded
+    pub icon: Option<IconSource>,
+    // char indices into `text` that matched the query, drawn in the highlight color
+    pub highlights: Vec<usize>,
+    // the first one is what Enter does, the rest go in the actions menu
+    pub actions: Vec<ResultAction>,
+}
+
+impl SearchResult {
+    pub fn action(&self) -> Option<&ResultAction> {
+        self.actions.first()
+    }
 }
 
 #[derive(Clone)]
 pub enum ResultAction {
-    Open { path: PathBuf },
-    Copy { text: String },
+    Open {
+        path: PathBuf,
+    },
+    Run {
+        path: PathBuf,
+        args: Vec<String>,
+        working_dir: Option<PathBuf>,
+    },
+    OpenWith {
+        path: PathBuf,
+        app: String,
+    },
+    // lists the indexed apps to pick one to open the path with
+    ChooseApp {
+        path: PathBuf,
+    },
+    OpenElevated {
+        path: PathBuf,
+    },
+    RevealInFolder {
+        path: PathBuf,
+    },
+    Copy {
+        text: String,
+    },
+    CopyPath {
+        path: PathBuf,
+    },
+    ClearHistory,
+    RunShell {
+        command: String,
+    },
+    RunShellDetached {
+        command: String,
+    },
+    RunInTerminal {
+        command: String,
+    },
+    // the output of the last `>` command
+    CopyOutput,
+    KillShell,
     Lua,
 }
 
+impl ResultAction {
+    pub fn label(&self) -> String {
+        match self {
+            ResultAction::Open { .. } => "open".to_string(),
+            ResultAction::Run { .. } => "run with arguments".to_string(),
+            ResultAction::OpenWith { app, .. } => format!("open with {}", app),
+            ResultAction::ChooseApp { .. } => "open with...".to_string(),
+            ResultAction::OpenElevated { .. } => "run as administrator".to_string(),
+            ResultAction::RevealInFolder { .. } => "show in folder".to_string(),
+            ResultAction::Copy { .. } => "copy".to_string(),
+            ResultAction::CopyPath { .. } => "copy path".to_string(),
+            ResultAction::ClearHistory => "clear history".to_string(),
+            ResultAction::RunShell { .. } => "run".to_string(),
+            ResultAction::RunShellDetached { .. } => "run detached".to_string(),
+            ResultAction::RunInTerminal { .. } => "run in terminal".to_string(),
+            ResultAction::CopyOutput => "copy output".to_string(),
+            ResultAction::KillShell => "stop".to_string(),
+            ResultAction::Lua => "run".to_string(),
+        }
+    }
+}
+
 pub struct Search {
     matcher: SkimMatcherV2,
-    shortcuts: Vec<PathBuf>,
+    shortcuts: Vec<Shortcut>,
     aliases: Map<String, String>,
+    inline_calculator: bool,
+    open_with: Vec<String>,
+    elevate_with: Vec<String>,
 
     custom_shortcuts: Vec<SearchResult>,
+    // markdown for the preview panel, by entry name
+    custom_previews: HashMap<String, String>,
+    calculator: RefCell<calc::Session>,
+    pending_calculation: RefCell<Option<calc::Evaluation>>,
+    last_calculation: RefCell<Vec<SearchResult>>,
+    query_history: RefCell<Vec<String>>,
+    shell: RefCell<shell::Shell>,
+    show_hidden_files: Cell<bool>,
+    files: files::FileIndex,
+    launches: RefCell<Launches>,
 }
 
-struct KeyMatch {
-    path: Option<PathBuf>,
+struct KeyMatch<'a> {
+    shortcut: Option<&'a Shortcut>,
     name: String,
     kind: Option<MatchKind>,
+    highlights: Vec<usize>,
 }
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
@@ -44,66 +154,677 @@ enum MatchKind {
     Fuzzy,
 }
 
+// `~/Doc`, `/usr/lo`, `C:\Us`
+fn is_path_query(input: &str) -> bool {
+    let bytes = input.as_bytes();
+    let drive = bytes.len() >= 3
+        && bytes[0].is_ascii_alphabetic()
+        && bytes[1] == b':'
+        && (bytes[2] == b'\\' || bytes[2] == b'/');
+
+    input.starts_with('/') || input.starts_with('~') || drive
+}
+
 impl Search {
-    pub fn new(shortcuts: Vec<PathBuf>, aliases: Map<String, String>) -> Self {
+    pub fn new(shortcuts: Vec<Shortcut>, config: &Config, ctx: egui::Context) -> Self {
         Self {
-            matcher: SkimMatcherV2::default(),
+            matcher: SkimMatcherV2::default().ignore_case(),
             shortcuts,
-            aliases,
+            aliases: config.search.aliases.clone(),
+            inline_calculator: config.search.inline_calculator,
+            open_with: config.search.open_with.clone(),
+            elevate_with: config.general.elevate_with.clone(),
 
             custom_shortcuts: Vec::new(),
+            custom_previews: HashMap::new(),
+            calculator: RefCell::new(calc::Session::new(&config.calculator)),
+            pending_calculation: RefCell::new(None),
+            last_calculation: RefCell::new(Vec::new()),
+            query_history: RefCell::new(Vec::new()),
+            shell: RefCell::new(shell::Shell::new(&config.shell)),
+            show_hidden_files: Cell::new(config.search.show_hidden_files),
+            files: files::FileIndex::start(&config.files, MAX_FILE_RESULTS, ctx),
+            launches: RefCell::new(Launches::load()),
         }
     }
 
-    pub fn add_custom_shortcut(&mut self, name: String) {
+    pub fn add_custom_shortcut(
+        &mut self,
+        name: String,
+        description: Option<String>,
+        preview: Option<String>,
+    ) {
+        if let Some(preview) = preview {
+            self.custom_previews.insert(name.clone(), preview);
+        }
+
         self.custom_shortcuts.push(SearchResult {
             mode: SearchMode::Search,
             text: name,
-            action: Some(ResultAction::Lua),
+            subtitle: description,
+            icon: None,
+            highlights: vec![],
+            actions: vec![ResultAction::Lua],
         });
     }
 
     pub fn search(&self, input: &str) -> Vec<SearchResult> {
         if input.is_empty() {
+            self.last_calculation.borrow_mut().clear();
             return vec![];
         }
 
         // TODO: find better way to do this
         let mode = if input.starts_with('=') {
             SearchMode::Calculator
+        } else if input.starts_with('>') {
+            SearchMode::Shell
+        } else if input.starts_with("f ") {
+            SearchMode::Files
+        } else if is_path_query(input) {
+            SearchMode::Path
         } else {
             SearchMode::Search
         };
 
         match mode {
             SearchMode::Calculator => self.mode_calculator(&input[1..]),
-            SearchMode::Search => self.mode_search(input),
+            SearchMode::Shell => self.mode_shell(input[1..].trim()),
+            SearchMode::Path => self.mode_path(input),
+            SearchMode::Files => self.mode_files(input[2..].trim()),
+            SearchMode::Search => {
+                self.last_calculation.borrow_mut().clear();
+                self.mode_search(input)
+            }
+        }
+    }
+
+    /// Records the pending calculation in the session (setting `ans` and any
+    /// assigned variable) if `text` is one of its results.
+    pub fn commit_calculation(&self, text: &str) {
+        let is_current = self
+            .last_calculation
+            .borrow()
+            .iter()
+            .any(|result| result.text == text);
+
+        if is_current {
+            if let Some(evaluation) = self.pending_calculation.borrow_mut().take() {
+                self.calculator.borrow_mut().commit(evaluation);
+            }
+        }
+    }
+
+    /// Remembers a query that was run, for `history-up`. Oldest first.
+    pub fn remember_query(&self, input: &str) {
+        if input.trim().is_empty() {
+            return;
+        }
+
+        let mut history = self.query_history.borrow_mut();
+        history.retain(|query| query != input);
+        history.push(input.to_string());
+        if history.len() > MAX_QUERY_HISTORY {
+            history.remove(0);
+        }
+    }
+
+    pub fn query_history(&self) -> Vec<String> {
+        self.query_history.borrow().clone()
+    }
+
+    // the file or shortcut a result opens
+    fn result_path(result: &SearchResult) -> Option<&Path> {
+        result.actions.iter().find_map(|action| match action {
+            ResultAction::Open { path } => Some(path.as_path()),
+            _ => None,
+        })
+    }
+
+    pub fn record_launch(&self, result: &SearchResult) {
+        if let Some(path) = Self::result_path(result) {
+            self.launches.borrow_mut().record(path);
+        }
+    }
+
+    /// More about a result than fits in its row, for the preview panel.
+    pub fn preview(&self, result: &SearchResult) -> Option<Preview> {
+        match result.mode {
+            SearchMode::Search => self.preview_entry(result),
+            SearchMode::Files | SearchMode::Path => {
+                let path = Self::result_path(result)?;
+                let mut details = preview::file_details(path);
+                details.extend(preview::launch_details(self.launches.borrow().get(path)));
+
+                Some(Preview {
+                    title: result.text.clone(),
+                    details,
+                    body: preview::file_body(path),
+                })
+            }
+            SearchMode::Calculator => self.preview_calculation(result),
+            SearchMode::Shell => None,
+        }
+    }
+
+    fn preview_entry(&self, result: &SearchResult) -> Option<Preview> {
+        let path = match Self::result_path(result) {
+            Some(path) => path,
+            // a lua entry
+            None => {
+                let details = result
+                    .subtitle
+                    .iter()
+                    .map(|description| ("description", description.clone()))
+                    .collect();
+                return Some(Preview {
+                    title: result.text.clone(),
+                    details,
+                    body: self
+                        .custom_previews
+                        .get(&result.text)
+                        .cloned()
+                        .map(Body::Markdown),
+                });
+            }
+        };
+        let shortcut = self
+            .shortcuts
+            .iter()
+            .find(|shortcut| shortcut.path == path)?;
+
+        let mut details = vec![("path", shortcut.path.display().to_string())];
+        if let Some(link) = &shortcut.link {
+            let fields = [
+                ("target", &link.target),
+                ("arguments", &link.arguments),
+                ("working dir", &link.working_dir),
+                ("description", &link.description),
+            ];
+            for (label, value) in fields {
+                if let Some(value) = value {
+                    details.push((label, value.clone()));
+                }
+            }
+        }
+        if let Some(bundle) = &shortcut.bundle {
+            let fields = [
+                ("bundle id", &bundle.identifier),
+                ("version", &bundle.version),
+            ];
+            for (label, value) in fields {
+                if let Some(value) = value {
+                    details.push((label, value.clone()));
+                }
+            }
+        }
+        details.extend(preview::launch_details(self.launches.borrow().get(path)));
+
+        Some(Preview {
+            title: shortcut.name.clone(),
+            details,
+            body: None,
+        })
+    }
+
+    // evaluated again for the alternatives, the result only has the one it shows
+    fn preview_calculation(&self, result: &SearchResult) -> Option<Preview> {
+        let input = result.subtitle.as_ref()?;
+        let mut outputs = if self.is_date_expr(input) {
+            date::eval(input).ok()?
+        } else {
+            let calculator = self.calculator.borrow();
+            let evaluation = calculator.eval(input).ok()?;
+            let alternatives = calculator.alternatives(&evaluation.value);
+            evaluation.outputs.into_iter().chain(alternatives).collect()
+        };
+
+        // the same text can come out of more than one format
+        let mut seen = HashSet::new();
+        outputs.retain(|output| seen.insert(output.display.clone()));
+
+        Some(Preview {
+            title: result.text.clone(),
+            details: vec![("expression", input.clone())],
+            body: Some(Body::Alternatives(outputs)),
+        })
+    }
+
+    pub fn elevate_with(&self) -> &[String] {
+        &self.elevate_with
+    }
+
+    pub fn clear_calculator_history(&self) {
+        self.calculator.borrow_mut().clear_history();
+    }
+
+    /// What Tab fills the input with for this result, if anything.
+    pub fn completion(&self, result: &SearchResult) -> Option<String> {
+        match result.mode {
+            SearchMode::Path if !result.actions.is_empty() => Some(result.text.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn toggle_hidden_files(&self) {
+        self.show_hidden_files.set(!self.show_hidden_files.get());
+    }
+
+    /// Runs a `>` command. `Err` means it couldn't be started at all.
+    pub fn run_shell(&self, action: &ResultAction) -> anyhow::Result<()> {
+        let mut shell = self.shell.borrow_mut();
+        match action {
+            ResultAction::RunShell { command } => shell.run(command),
+            ResultAction::RunShellDetached { command } => shell.run_detached(command),
+            ResultAction::RunInTerminal { command } => shell.run_in_terminal(command),
+            _ => Ok(()),
+        }
+    }
+
+    /// True while a `>` command is still producing output.
+    pub fn is_busy(&self) -> bool {
+        self.shell.borrow().is_running()
+    }
+
+    pub fn kill_shell(&self) -> anyhow::Result<()> {
+        self.shell.borrow().kill()
+    }
+
+    pub fn shell_output(&self) -> String {
+        self.shell.borrow().output().unwrap_or_default()
+    }
+
+    fn shell_actions(command: &str) -> Vec<ResultAction> {
+        let command = command.to_string();
+        vec![
+            ResultAction::RunShell {
+                command: command.clone(),
+            },
+            ResultAction::RunInTerminal {
+                command: command.clone(),
+            },
+            ResultAction::RunShellDetached {
+                command: command.clone(),
+            },
+            ResultAction::Copy { text: command },
+        ]
+    }
+
+    fn mode_shell(&self, command: &str) -> Vec<SearchResult> {
+        let shell = self.shell.borrow();
+        let mut results = Vec::new();
+
+        if !command.is_empty() {
+            results.push(SearchResult {
+                mode: SearchMode::Shell,
+                text: format!("run `{}`", command),
+                subtitle: None,
+                icon: None,
+                highlights: vec![],
+                actions: Self::shell_actions(command),
+            });
+        }
+
+        let max_lines = shell.max_lines();
+        let output = shell.with_current(|run| {
+            let status = match (run.running, run.status) {
+                (true, _) => "running...".to_string(),
+                (false, _) if run.killed => "stopped".to_string(),
+                (false, Some(code)) => format!("exited with {}", code),
+                (false, None) => "killed".to_string(),
+            };
+
+            let mut actions = vec![ResultAction::CopyOutput];
+            if run.running {
+                actions.push(ResultAction::KillShell);
+            }
+            actions.extend([
+                ResultAction::RunShell {
+                    command: run.command.clone(),
+                },
+                ResultAction::RunInTerminal {
+                    command: run.command.clone(),
+                },
+            ]);
+
+            let line = |text: String| SearchResult {
+                mode: SearchMode::Shell,
+                text,
+                subtitle: None,
+                icon: None,
+                highlights: vec![],
+                actions: vec![],
+            };
+
+            let mut results = vec![SearchResult {
+                text: format!("$ {} ({})", run.command, status),
+                actions,
+                ..line(String::new())
+            }];
+
+            // the end of the output, where a running command is at
+            let skipped = run.output.len().saturating_sub(max_lines);
+            if run.dropped + skipped > 0 {
+                let earlier = format!("... {} earlier lines", run.dropped + skipped);
+                results.push(line(earlier));
+            }
+            results.extend(run.output.iter().skip(skipped).cloned().map(line));
+
+            results
+        });
+        results.extend(output.into_iter().flatten());
+
+        for previous in shell.history.iter().rev() {
+            if previous == command {
+                continue;
+            }
+
+            results.push(SearchResult {
+                mode: SearchMode::Shell,
+                text: format!("> {}", previous),
+                subtitle: None,
+                icon: None,
+                highlights: vec![],
+                actions: Self::shell_actions(previous),
+            });
+        }
+
+        results
+    }
+
+    fn mode_files(&self, query: &str) -> Vec<SearchResult> {
+        let status = |text: &str| {
+            vec![SearchResult {
+                mode: SearchMode::Files,
+                text: text.to_string(),
+                subtitle: None,
+                icon: None,
+                highlights: vec![],
+                actions: vec![],
+            }]
+        };
+
+        if !self.files.is_enabled() {
+            return status("no roots set in [files] in the config");
+        }
+        if query.is_empty() {
+            return vec![];
+        }
+
+        match self.files.find(query) {
+            Some(found) => found
+                .into_iter()
+                .map(|(path, highlights)| SearchResult {
+                    mode: SearchMode::Files,
+                    text: path.file_name().unwrap().to_string_lossy().into_owned(),
+                    subtitle: path.parent().map(|folder| folder.display().to_string()),
+                    icon: Some(IconSource::for_file(&path, false)),
+                    highlights,
+                    actions: self.open_actions(&path),
+                })
+                .collect(),
+            None => status("indexing files..."),
         }
     }
 
+    fn mode_path(&self, input: &str) -> Vec<SearchResult> {
+        // everything up to the last separator is the directory, the rest filters it
+        let (dir_text, partial) = match input.rfind(['/', '\\']) {
+            Some(i) => (input[..=i].to_string(), &input[i + 1..]),
+            None => (format!("{}{}", input, std::path::MAIN_SEPARATOR), ""),
+        };
+        let separator = dir_text.chars().last().unwrap();
+        let dir = PathBuf::from(shellexpand::tilde(&dir_text).as_ref());
+
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                return vec![SearchResult {
+                    mode: SearchMode::Path,
+                    text: format!("! {}", e),
+                    subtitle: None,
+                    icon: None,
+                    highlights: vec![],
+                    actions: vec![],
+                }]
+            }
+        };
+
+        let partial_lowercase = partial.to_lowercase();
+        let show_hidden = self.show_hidden_files.get() || partial.starts_with('.');
+
+        let mut matches: Vec<(bool, String, PathBuf)> = entries
+            .filter_map(Result::ok)
+            .filter(|entry| show_hidden || !util::is_hidden(&entry.path()))
+            .filter_map(|entry| {
+                let name = entry.file_name().to_str()?.to_string();
+                if !name.to_lowercase().starts_with(&partial_lowercase) {
+                    return None;
+                }
+
+                Some((entry.path().is_dir(), name, entry.path()))
+            })
+            .collect();
+
+        // directories first
+        matches.sort_by_cached_key(|(is_dir, name, _)| (!is_dir, name.to_lowercase()));
+
+        let mut results = Vec::new();
+        if partial.is_empty() {
+            results.push(SearchResult {
+                mode: SearchMode::Path,
+                text: dir_text.clone(),
+                subtitle: None,
+                icon: Some(IconSource::for_file(&dir, true)),
+                highlights: vec![],
+                actions: self.open_actions(&dir),
+            });
+        }
+
+        for (is_dir, name, path) in matches.into_iter().take(MAX_PATH_RESULTS) {
+            let mut text = format!("{}{}", dir_text, name);
+            if is_dir {
+                text.push(separator);
+            }
+
+            results.push(SearchResult {
+                mode: SearchMode::Path,
+                text,
+                subtitle: None,
+                icon: Some(IconSource::for_file(&path, is_dir)),
+                highlights: vec![],
+                actions: self.open_actions(&path),
+            });
+        }
+
+        results
+    }
+
     fn mode_calculator(&self, input: &str) -> Vec<SearchResult> {
-        let r = meval::eval_str(input.trim());
+        let mut results = if input.trim().is_empty() {
+            vec![]
+        } else {
+            self.calculate(input)
+        };
+
+        let calculator = self.calculator.borrow();
+        for entry in calculator.history.iter().rev() {
+            results.push(SearchResult {
+                mode: SearchMode::Calculator,
+                text: format!("{} = {}", entry.input, entry.output.display),
+                subtitle: None,
+                icon: None,
+                highlights: vec![],
+                actions: vec![ResultAction::Copy {
+                    text: entry.output.value.clone(),
+                }],
+            });
+        }
+
+        if !calculator.history.is_empty() {
+            results.push(SearchResult {
+                mode: SearchMode::Calculator,
+                text: "clear history".to_string(),
+                subtitle: None,
+                icon: None,
+                highlights: vec![],
+                actions: vec![ResultAction::ClearHistory],
+            });
+        }
 
-        let res = if let Ok(n) = r {
-            n.to_string()
+        results
+    }
+
+    // `now` and friends are the calculator's if the user made a variable called that
+    fn is_date_expr(&self, input: &str) -> bool {
+        let calculator = self.calculator.borrow();
+        date::is_date_expr(input, |name| calculator.has_var(name))
+    }
+
+    fn calculate(&self, input: &str) -> Vec<SearchResult> {
+        // dates don't go into the numeric session (no `ans`, no variables)
+        let evaluated = if self.is_date_expr(input) {
+            date::eval(input).map(|outputs| (None, outputs, None))
         } else {
-            "ERROR".to_string()
+            self.calculator.borrow().eval(input).map(|evaluation| {
+                let name = evaluation.name.clone();
+                let outputs = evaluation.outputs.clone();
+                (name, outputs, Some(evaluation))
+            })
         };
 
-        vec![SearchResult {
-            mode: SearchMode::Calculator,
-            text: format!("= {}", res),
-            action: Some(ResultAction::Copy { text: res }),
-        }]
+        match evaluated {
+            Ok((name, outputs, evaluation)) => {
+                let results: Vec<SearchResult> = outputs
+                    .iter()
+                    .map(|output| SearchResult {
+                        mode: SearchMode::Calculator,
+                        text: match &name {
+                            Some(name) => format!("{} = {}", name, output.display),
+                            None => format!("= {}", output.display),
+                        },
+                        subtitle: Some(input.trim().to_string()),
+                        icon: None,
+                        highlights: vec![],
+                        actions: vec![ResultAction::Copy {
+                            text: output.value.clone(),
+                        }],
+                    })
+                    .collect();
+
+                *self.last_calculation.borrow_mut() = results.clone();
+                *self.pending_calculation.borrow_mut() = evaluation;
+                results
+            }
+            Err(e) => {
+                *self.pending_calculation.borrow_mut() = None;
+
+                // keep showing the last thing that worked while the user is mid-expression
+                let mut results = vec![SearchResult {
+                    mode: SearchMode::Calculator,
+                    text: format!("! {}", e),
+                    subtitle: None,
+                    icon: None,
+                    highlights: vec![],
+                    actions: vec![],
+                }];
+                results.extend(self.last_calculation.borrow().iter().cloned());
+                results
+            }
+        }
     }
 
-    fn do_keymatch(
+    fn open_actions(&self, path: &Path) -> Vec<ResultAction> {
+        let mut actions = vec![
+            ResultAction::Open {
+                path: path.to_path_buf(),
+            },
+            ResultAction::RevealInFolder {
+                path: path.to_path_buf(),
+            },
+            ResultAction::CopyPath {
+                path: path.to_path_buf(),
+            },
+        ];
+
+        // windows has UAC and macos its password prompt, elsewhere needs a wrapper like
+        // pkexec configured
+        let has_prompt = cfg!(any(target_os = "windows", target_os = "macos"));
+        if has_prompt || !self.elevate_with.is_empty() {
+            actions.insert(
+                1,
+                ResultAction::OpenElevated {
+                    path: path.to_path_buf(),
+                },
+            );
+        }
+
+        for app in &self.open_with {
+            actions.push(ResultAction::OpenWith {
+                path: path.to_path_buf(),
+                app: app.clone(),
+            });
+        }
+        actions.push(ResultAction::ChooseApp {
+            path: path.to_path_buf(),
+        });
+
+        actions
+    }
+
+    /// The indexed apps matching `input` to open `path` with, for "open with...". All of
+    /// them when nothing is typed.
+    pub fn app_choices(&self, path: &Path, input: &str) -> Vec<SearchResult> {
+        let mut matches: Vec<KeyMatch> = self
+            .shortcuts
+            .iter()
+            // a shortcut to a website can't open files
+            .filter(|shortcut| shortcut.link.is_none() || shortcut.target().is_some())
+            .map(|shortcut| {
+                let fuzzy = self.fuzzy_indices(&shortcut.name, input);
+                Self::do_keymatch(shortcut.name.clone(), Some(shortcut), None, input, fuzzy)
+            })
+            .filter(|km| km.kind.is_some())
+            .collect();
+        matches.sort_by_cached_key(|km| (km.kind, km.name.to_lowercase()));
+
+        matches
+            .into_iter()
+            .filter_map(|km| {
+                let shortcut = km.shortcut?;
+                // run what a parsed shortcut points at, or the app itself
+                let app = shortcut.target().unwrap_or_else(|| shortcut.path.clone());
+
+                Some(SearchResult {
+                    mode: SearchMode::Search,
+                    text: km.name,
+                    subtitle: Some(shortcut.subtitle()),
+                    icon: Some(shortcut.icon()),
+                    highlights: km.highlights,
+                    actions: vec![ResultAction::OpenWith {
+                        path: path.to_path_buf(),
+                        app: app.display().to_string(),
+                    }],
+                })
+            })
+            .collect()
+    }
+
+    // on the text as it is, lowercasing can change how many chars there are
+    fn fuzzy_indices(&self, text: &str, input: &str) -> Option<Vec<usize>> {
+        self.matcher
+            .fuzzy_indices(text, input)
+            .map(|(_, indices)| indices)
+    }
+
+    fn do_keymatch<'a>(
         name: String,
-        path: Option<PathBuf>,
+        shortcut: Option<&'a Shortcut>,
         alias: Option<&String>,
         input: &str,
-        fuzzy: bool,
-    ) -> KeyMatch {
+        // where the name matched, empty when only a keyword did
+        fuzzy: Option<Vec<usize>>,
+    ) -> KeyMatch<'a> {
         let alias_matches = alias.is_some() && name.trim() == alias.unwrap().trim();
         let exact_match = name.trim().to_lowercase() == input.trim().to_lowercase();
         let starts_with = name
@@ -111,30 +832,140 @@ impl Search {
             .to_lowercase()
             .starts_with(&input.trim().to_lowercase());
 
-        let match_kind = if alias_matches {
-            Some(MatchKind::Alias)
+        // the trimmed name is what's compared, so skip past any leading whitespace
+        let leading = name.chars().take_while(|c| c.is_whitespace()).count();
+        let prefix = |len: usize| (leading..leading + len).collect();
+
+        let (match_kind, highlights) = if alias_matches {
+            (Some(MatchKind::Alias), vec![])
         } else if exact_match {
-            Some(MatchKind::Exact)
+            (Some(MatchKind::Exact), prefix(name.trim().chars().count()))
         } else if starts_with {
-            Some(MatchKind::StartsWith)
-        } else if fuzzy {
-            Some(MatchKind::Fuzzy)
+            (
+                Some(MatchKind::StartsWith),
+                prefix(input.trim().chars().count()),
+            )
+        } else if let Some(indices) = fuzzy {
+            (Some(MatchKind::Fuzzy), indices)
         } else {
-            None
+            (None, vec![])
         };
 
         KeyMatch {
-            path,
+            shortcut,
             name,
             kind: match_kind,
+            highlights,
+        }
+    }
+
+    // only bother with things that look like arithmetic, so "7zip" stays a search
+    fn inline_calculation(&self, input: &str) -> Option<SearchResult> {
+        let looks_like_math = input.chars().any(|c| c.is_ascii_digit())
+            && input.chars().any(|c| "+-*/^%!()&|<>".contains(c))
+            && !input.contains('=');
+        if !looks_like_math || self.is_date_expr(input) {
+            return None;
+        }
+
+        let evaluation = self.calculator.borrow().eval(input).ok()?;
+        let output = evaluation.outputs.first()?.clone();
+        let result = SearchResult {
+            mode: SearchMode::Calculator,
+            text: format!("= {}", output.display),
+            subtitle: Some(input.trim().to_string()),
+            icon: None,
+            highlights: vec![],
+            actions: vec![ResultAction::Copy { text: output.value }],
+        };
+
+        *self.last_calculation.borrow_mut() = vec![result.clone()];
+        *self.pending_calculation.borrow_mut() = Some(evaluation);
+        Some(result)
+    }
+
+    // `code ~/projects/foo` or `vs code -- --new-window`: everything after a known
+    // entry name (or alias), or after ` -- `, is passed to the result as arguments
+    fn split_arguments<'a>(&self, input: &'a str) -> (&'a str, Option<&'a str>) {
+        if let Some((query, args)) = input.split_once(" -- ") {
+            return (query, Some(args));
+        }
+
+        let lowercase = input.to_ascii_lowercase();
+        let names: Vec<String> = self
+            .aliases
+            .keys()
+            .cloned()
+            .chain(self.shortcuts.iter().map(|shortcut| shortcut.name.clone()))
+            .map(|name| name.to_ascii_lowercase())
+            .collect();
+
+        // still typing a longer name, e.g. "visual studio" when there's also a "visual"
+        if names.iter().any(|name| name.starts_with(&lowercase)) {
+            return (input, None);
+        }
+
+        let name_len = names
+            .iter()
+            .filter(|name| {
+                lowercase
+                    .strip_prefix(name.as_str())
+                    .is_some_and(|rest| rest.starts_with(' ') && !rest.trim().is_empty())
+            })
+            .map(|name| name.len())
+            .max();
+
+        match name_len {
+            Some(len) => (&input[..len], Some(input[len..].trim())),
+            None => (input, None),
+        }
+    }
+
+    fn shortcut_result(&self, shortcut: &Shortcut, args: Option<&[String]>) -> SearchResult {
+        let mut actions = self.open_actions(&shortcut.path);
+
+        if let Some(args) = args {
+            // run what a parsed shortcut points at directly, after its own arguments
+            let run = match (shortcut.target(), &shortcut.link) {
+                (Some(target), Some(link)) => ResultAction::Run {
+                    path: target,
+                    args: link
+                        .arguments
+                        .iter()
+                        .flat_map(|arguments| util::split_args(arguments))
+                        .chain(args.iter().cloned())
+                        .collect(),
+                    working_dir: link.working_dir.as_ref().map(PathBuf::from),
+                },
+                _ => ResultAction::Run {
+                    path: shortcut.path.clone(),
+                    args: args.to_vec(),
+                    working_dir: None,
+                },
+            };
+            actions.insert(0, run);
+        }
+
+        SearchResult {
+            mode: SearchMode::Search,
+            text: shortcut.name.clone(),
+            subtitle: Some(shortcut.subtitle()),
+            icon: Some(shortcut.icon()),
+            highlights: vec![],
+            actions,
         }
     }
 
     fn mode_search(&self, input: &str) -> Vec<SearchResult> {
-        let alias = self.aliases.get(input.trim());
+        let mut results = Vec::new();
+        if self.inline_calculator {
+            results.extend(self.inline_calculation(input));
+        }
 
-        let shortcuts = self.shortcuts.clone();
-        let shortcuts: Vec<Option<&PathBuf>> = shortcuts.iter().map(Some).collect();
+        let (input, args) = self.split_arguments(input);
+        let args = args.map(util::split_args);
+
+        let alias = self.aliases.get(input.trim());
 
         // we need to prioritize, in order:
         // - aliases
@@ -142,25 +973,24 @@ impl Search {
         // - starts with input
         // - everything else
         let mut vec: Vec<KeyMatch> = Vec::new();
-        for shortcut in shortcuts {
-            let path = shortcut.unwrap().to_path_buf();
-            let name = path.file_stem().unwrap().to_str().unwrap().to_string();
+        for shortcut in &self.shortcuts {
+            let name = shortcut.name.clone();
 
-            let fuzzy = self
-                .matcher
-                .fuzzy_match(&name.to_lowercase(), &input.to_lowercase())
-                .is_some();
+            let fuzzy = self.fuzzy_indices(&name, input).or_else(|| {
+                shortcut
+                    .keywords
+                    .iter()
+                    .any(|keyword| self.fuzzy_indices(keyword, input).is_some())
+                    .then(Vec::new)
+            });
 
-            let km = Self::do_keymatch(name, Some(path), alias, input, fuzzy);
+            let km = Self::do_keymatch(name, Some(shortcut), alias, input, fuzzy);
             vec.push(km);
         }
 
         for custom_shortcut in &self.custom_shortcuts {
             let name = custom_shortcut.text.clone();
-            let fuzzy = self
-                .matcher
-                .fuzzy_match(&name.to_lowercase(), &input.to_lowercase())
-                .is_some();
+            let fuzzy = self.fuzzy_indices(&name, input);
 
             let km = Self::do_keymatch(name, None, alias, input, fuzzy);
             vec.push(km);
@@ -171,28 +1001,29 @@ impl Search {
 
         available_shortcuts.sort_by_cached_key(|x| x.kind);
 
-        available_shortcuts
+        let matches = available_shortcuts
             .iter()
-            .map(|k| {
-                let path = &k.path;
-                let name = &k.name;
-
-                if let Some(path) = path {
-                    SearchResult {
-                        mode: SearchMode::Search,
-                        text: name.to_string(),
-                        action: Some(ResultAction::Open {
-                            path: path.to_owned(),
-                        }),
-                    }
-                } else {
-                    SearchResult {
-                        mode: SearchMode::Search,
-                        text: name.to_string(),
-                        action: Some(ResultAction::Lua),
-                    }
-                }
+            .map(|k| match k.shortcut {
+                Some(shortcut) => SearchResult {
+                    highlights: k.highlights.clone(),
+                    ..self.shortcut_result(shortcut, args.as_deref())
+                },
+                None => SearchResult {
+                    mode: SearchMode::Search,
+                    text: k.name.to_string(),
+                    subtitle: self
+                        .custom_shortcuts
+                        .iter()
+                        .find(|custom_shortcut| custom_shortcut.text == k.name)
+                        .and_then(|custom_shortcut| custom_shortcut.subtitle.clone()),
+                    icon: None,
+                    highlights: k.highlights.clone(),
+                    actions: vec![ResultAction::Lua],
+                },
             })
-            .collect()
+            .collect::<Vec<_>>();
+
+        results.extend(matches);
+        results
     }
 }
@@ -0,0 +1,241 @@
+// `>` mode: runs the rest of the query through the configured shell and collects its output
+
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::config;
+
+const MAX_HISTORY: usize = 50;
+// kept for copying, something like `yes` would fill up memory otherwise
+const MAX_OUTPUT_LINES: usize = 10_000;
+// how often the exit status is checked, `kill` locks the child in between
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Default)]
+pub struct Run {
+    pub command: String,
+    // the last MAX_OUTPUT_LINES lines, stdout and stderr interleaved in the order they
+    // arrived
+    pub output: VecDeque<String>,
+    // lines that fell off the front of `output`
+    pub dropped: usize,
+    pub running: bool,
+    pub status: Option<i32>,
+    // stopped with `kill`
+    pub killed: bool,
+}
+
+pub struct Shell {
+    config: config::Shell,
+    current: Option<Arc<Mutex<Run>>>,
+    child: Option<Arc<Mutex<Child>>>,
+    pub history: Vec<String>,
+}
+
+// the command goes last, after e.g. `cmd /C` or `sh -c`
+fn build_command(parts: &[String], command: &str) -> anyhow::Result<Command> {
+    let (program, args) = parts
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("no shell command set in config"))?;
+
+    let mut process = Command::new(program);
+    process.args(args);
+
+    // cmd does its own parsing, so don't let std quote the command for it
+    #[cfg(target_os = "windows")]
+    std::os::windows::process::CommandExt::raw_arg(&mut process, command);
+    #[cfg(not(target_os = "windows"))]
+    process.arg(command);
+
+    Ok(process)
+}
+
+// keeps a console from flashing up behind the launcher
+fn hide_window(process: &mut Command) -> &mut Command {
+    #[cfg(target_os = "windows")]
+    std::os::windows::process::CommandExt::creation_flags(process, 0x08000000); // CREATE_NO_WINDOW
+
+    process
+}
+
+fn read_lines(stream: impl Read + Send + 'static, run: Arc<Mutex<Run>>) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let line = match line {
+                Ok(line) => line,
+                Err(_) => break,
+            };
+
+            let mut run = run.lock().unwrap();
+            if run.output.len() == MAX_OUTPUT_LINES {
+                run.output.pop_front();
+                run.dropped += 1;
+            }
+            run.output.push_back(line);
+        }
+    })
+}
+
+// waits for it in the background, so it doesn't stay around as a zombie until tistow
+// exits
+fn reap(mut child: Child) {
+    std::thread::spawn(move || child.wait());
+}
+
+fn wait(child: &Mutex<Child>) -> Option<i32> {
+    loop {
+        match child.lock().unwrap().try_wait() {
+            Ok(Some(status)) => return status.code(),
+            Ok(None) => {}
+            Err(_) => return None,
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+impl Shell {
+    pub fn new(config: &config::Shell) -> Self {
+        Self {
+            config: config.clone(),
+            current: None,
+            child: None,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn max_lines(&self) -> usize {
+        self.config.max_lines
+    }
+
+    fn remember(&mut self, command: &str) {
+        self.history.retain(|previous| previous != command);
+        self.history.push(command.to_string());
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+    }
+
+    /// Starts `command` in the background, its output shows up in `with_current`.
+    pub fn run(&mut self, command: &str) -> anyhow::Result<()> {
+        self.remember(command);
+
+        let mut process = build_command(&self.config.command, command)?;
+        // so `kill` gets whatever the shell started too
+        #[cfg(not(target_os = "windows"))]
+        std::os::unix::process::CommandExt::process_group(&mut process, 0);
+
+        let mut child = hide_window(&mut process)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let run = Arc::new(Mutex::new(Run {
+            command: command.to_string(),
+            running: true,
+            ..Run::default()
+        }));
+
+        let readers = [
+            read_lines(child.stdout.take().unwrap(), run.clone()),
+            read_lines(child.stderr.take().unwrap(), run.clone()),
+        ];
+        let child = Arc::new(Mutex::new(child));
+
+        std::thread::spawn({
+            let run = run.clone();
+            let child = child.clone();
+            move || {
+                let status = wait(&child);
+                // the last lines can still be on their way after it exits
+                for reader in readers {
+                    reader.join().ok();
+                }
+
+                let mut run = run.lock().unwrap();
+                run.running = false;
+                run.status = status;
+            }
+        });
+
+        self.current = Some(run);
+        self.child = Some(child);
+        Ok(())
+    }
+
+    /// Stops the command started with `run` and everything it started, if it's still
+    /// going. Killing only the shell would leave e.g. `sleep` holding the output open.
+    pub fn kill(&self) -> anyhow::Result<()> {
+        let (run, child) = match (&self.current, &self.child) {
+            (Some(run), Some(child)) if self.is_running() => (run, child),
+            _ => return Ok(()),
+        };
+        // locked so it isn't reaped, and its pid reused, halfway through
+        let child = child.lock().unwrap();
+        let pid = child.id();
+
+        #[cfg(target_os = "windows")]
+        let mut kill = Command::new("taskkill");
+        #[cfg(target_os = "windows")]
+        kill.args(["/T", "/F", "/PID", &pid.to_string()]);
+        #[cfg(not(target_os = "windows"))]
+        let mut kill = Command::new("kill");
+        #[cfg(not(target_os = "windows"))]
+        kill.args(["-s", "KILL", "--", &format!("-{}", pid)]);
+
+        let status = hide_window(&mut kill)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()?;
+        if !status.success() {
+            anyhow::bail!("{:?} failed with {}", kill.get_program(), status);
+        }
+
+        run.lock().unwrap().killed = true;
+        Ok(())
+    }
+
+    /// Starts `command` without keeping track of it.
+    pub fn run_detached(&mut self, command: &str) -> anyhow::Result<()> {
+        self.remember(command);
+
+        let child = hide_window(&mut build_command(&self.config.command, command)?)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        reap(child);
+        Ok(())
+    }
+
+    pub fn run_in_terminal(&mut self, command: &str) -> anyhow::Result<()> {
+        self.remember(command);
+
+        reap(build_command(&self.config.terminal, command)?.spawn()?);
+        Ok(())
+    }
+
+    /// Calls `f` with the last command started with `run`, while it's locked.
+    pub fn with_current<R>(&self, f: impl FnOnce(&Run) -> R) -> Option<R> {
+        self.current.as_ref().map(|run| f(&run.lock().unwrap()))
+    }
+
+    /// Everything kept of the last command's output, to copy.
+    pub fn output(&self) -> Option<String> {
+        self.with_current(|run| {
+            let lines: Vec<&str> = run.output.iter().map(String::as_str).collect();
+            lines.join("\n")
+        })
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.current
+            .as_ref()
+            .is_some_and(|run| run.lock().unwrap().running)
+    }
+}
@@ -1,9 +1,13 @@
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
 };
 
-use crate::config::Config;
+use crate::bundle::{self, Bundle};
+use crate::config::{self, Config};
+use crate::icons::IconSource;
+use crate::ignore::RootFilter;
+use crate::link::{self, Link};
 use device_query::{DeviceQuery, DeviceState, Keycode};
 use egui::Color32;
 
@@ -24,73 +28,537 @@ pub fn hex_to_color32(hex: &str) -> Color32 {
 }
 
 #[cfg(target_os = "windows")]
-pub fn get_shortcuts(config: &Config) -> Vec<PathBuf> {
-    config
-        .search
-        .shortcut_paths
+pub fn reveal_in_folder(path: &Path) -> std::io::Result<()> {
+    use std::os::windows::process::CommandExt;
+
+    // explorer wants the quotes around the path only, not the whole argument
+    std::process::Command::new("explorer")
+        .raw_arg(format!("/select,\"{}\"", path.display()))
+        .spawn()?;
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+pub fn reveal_in_folder(path: &Path) -> std::io::Result<()> {
+    std::process::Command::new("open")
+        .arg("-R")
+        .arg(path)
+        .spawn()?;
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn shell_execute(
+    verb: &str,
+    path: &Path,
+    parameters: &str,
+    directory: Option<&Path>,
+) -> anyhow::Result<()> {
+    use std::{ffi::OsStr, os::windows::ffi::OsStrExt};
+    use windows::{
+        core::PCWSTR,
+        Win32::{Foundation::HWND, UI::Shell::ShellExecuteW},
+    };
+
+    let wide = |s: &OsStr| s.encode_wide().chain(Some(0)).collect::<Vec<u16>>();
+    let verb = wide(OsStr::new(verb));
+    let file = wide(path.as_os_str());
+    let parameters = wide(OsStr::new(parameters));
+    let directory = directory.map(|directory| wide(directory.as_os_str()));
+
+    let result = unsafe {
+        ShellExecuteW(
+            HWND(0),
+            PCWSTR(verb.as_ptr()),
+            PCWSTR(file.as_ptr()),
+            PCWSTR(parameters.as_ptr()),
+            directory
+                .as_ref()
+                .map_or(PCWSTR::null(), |directory| PCWSTR(directory.as_ptr())),
+            1, // SW_SHOWNORMAL
+        )
+    };
+
+    // anything up to 32 is an error code, e.g. 5 when the UAC prompt is declined
+    if result.0 <= 32 {
+        anyhow::bail!("ShellExecuteW failed with {}", result.0);
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+pub fn open_elevated(path: &Path, _elevate_with: &[String]) -> anyhow::Result<()> {
+    shell_execute("runas", path, "", None)
+}
+
+#[cfg(target_os = "macos")]
+pub fn open_elevated(path: &Path, elevate_with: &[String]) -> anyhow::Result<()> {
+    // a .app is a folder, only the executable inside it can be run as root
+    let program = match bundle::read(path)?.and_then(|bundle| bundle.executable) {
+        Some(executable) => path.join("Contents").join("MacOS").join(executable),
+        None => path.to_path_buf(),
+    };
+
+    if !elevate_with.is_empty() {
+        return run_elevated(elevate_with, &program);
+    }
+
+    // the system password prompt, backgrounded so osascript returns once it's started
+    let child = std::process::Command::new("osascript")
+        .args(["-e", "on run argv"])
+        .args([
+            "-e",
+            "do shell script quoted form of item 1 of argv & \" > /dev/null 2>&1 &\" \
+             with administrator privileges",
+        ])
+        .args(["-e", "end run"])
+        .arg(&program)
+        .stderr(std::process::Stdio::piped())
+        .spawn()?;
+    report_failure("osascript", child);
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub fn open_elevated(path: &Path, elevate_with: &[String]) -> anyhow::Result<()> {
+    run_elevated(elevate_with, path)
+}
+
+// `elevate_with` with the program appended, e.g. `pkexec /usr/bin/foo`
+#[cfg(not(target_os = "windows"))]
+fn run_elevated(elevate_with: &[String], program: &Path) -> anyhow::Result<()> {
+    let (wrapper, args) = elevate_with
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("no elevate_with command in config"))?;
+
+    let mut command = std::process::Command::new(wrapper);
+    command.args(args);
+    // there's no terminal for sudo to ask for the password on, -A uses SUDO_ASKPASS
+    let is_sudo = Path::new(wrapper).file_name() == Some("sudo".as_ref());
+    if is_sudo && !args.iter().any(|arg| arg == "-A" || arg == "--askpass") {
+        command.arg("-A");
+    }
+
+    let child = command
+        .arg(program)
+        .stderr(std::process::Stdio::piped())
+        .spawn()?;
+    report_failure(wrapper, child);
+    Ok(())
+}
+
+// a cancelled or failed password prompt only shows up in the exit code
+#[cfg(not(target_os = "windows"))]
+fn report_failure(wrapper: &str, child: std::process::Child) {
+    let wrapper = wrapper.to_string();
+    std::thread::spawn(move || match child.wait_with_output() {
+        Ok(output) if !output.status.success() => println!(
+            "couldn't launch elevated, {} {}: {}",
+            wrapper,
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Ok(_) => {}
+        Err(e) => println!("couldn't wait for {}: {}", wrapper, e),
+    });
+}
+
+pub fn is_hidden(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default();
+    if name.to_string_lossy().starts_with('.') {
+        return true;
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::fs::MetadataExt;
+
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        if let Ok(metadata) = std::fs::symlink_metadata(path) {
+            return metadata.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0;
+        }
+    }
+
+    false
+}
+
+/// Splits arguments typed into the search box on whitespace, keeping "quoted parts"
+/// together and expanding a leading `~`.
+pub fn split_args(input: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut started = false;
+
+    for c in input.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                started = true;
+            }
+            c if c.is_whitespace() && !quoted => {
+                if started {
+                    args.push(std::mem::take(&mut current));
+                    started = false;
+                }
+            }
+            c => {
+                current.push(c);
+                started = true;
+            }
+        }
+    }
+    if started {
+        args.push(current);
+    }
+
+    args.into_iter()
+        .map(|arg| shellexpand::tilde(&arg).into_owned())
+        .collect()
+}
+
+fn quote(arg: &str) -> String {
+    if arg.is_empty() || arg.contains([' ', '\t', '"']) {
+        format!("\"{}\"", arg.replace('"', "\\\""))
+    } else {
+        arg.to_string()
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub fn command_line(path: &Path, args: &[String]) -> String {
+    let mut parts = vec![quote(&path.display().to_string())];
+    parts.extend(args.iter().map(|arg| quote(arg)));
+    parts.join(" ")
+}
+
+#[cfg(target_os = "windows")]
+pub fn run_with_args(
+    path: &Path,
+    args: &[String],
+    working_dir: Option<&Path>,
+) -> anyhow::Result<()> {
+    // ShellExecute hands the parameters on to whatever a .lnk points at
+    let parameters: Vec<String> = args.iter().map(|arg| quote(arg)).collect();
+    shell_execute("open", path, &parameters.join(" "), working_dir)
+}
+
+#[cfg(target_os = "macos")]
+pub fn command_line(path: &Path, args: &[String]) -> String {
+    let mut parts = vec![
+        "open -a".to_string(),
+        quote(&path.display().to_string()),
+        "--args".to_string(),
+    ];
+    parts.extend(args.iter().map(|arg| quote(arg)));
+    parts.join(" ")
+}
+
+#[cfg(target_os = "macos")]
+pub fn run_with_args(
+    path: &Path,
+    args: &[String],
+    working_dir: Option<&Path>,
+) -> anyhow::Result<()> {
+    let mut command = std::process::Command::new("open");
+    if let Some(working_dir) = working_dir {
+        command.current_dir(working_dir);
+    }
+
+    command
+        .arg("-a")
+        .arg(path)
+        .arg("--args")
+        .args(args)
+        .spawn()?;
+    Ok(())
+}
+
+pub struct Shortcut {
+    pub path: PathBuf,
+    // the file name without its extension (or an app's display name), after the root's prefix
+    pub name: String,
+    // matched like the name, but not shown
+    pub keywords: Vec<String>,
+    // what a .lnk or .url file points at
+    pub link: Option<Link>,
+    // the link's target is gone, checked once when it's indexed
+    pub broken: bool,
+    pub bundle: Option<Bundle>,
+    // the shortcut_paths entry it was found in
+    pub source: PathBuf,
+    // when there are other copies of it and show_duplicates is on
+    pub show_source: bool,
+}
+
+impl Shortcut {
+    /// The target of a parsed shortcut when it's a file, to run it directly.
+    pub fn target(&self) -> Option<PathBuf> {
+        let target = self.link.as_ref()?.target.as_ref()?;
+        (!target.contains("://")).then(|| PathBuf::from(target))
+    }
+
+    /// Where to get the icon from: the shortcut's own icon location, else whatever it
+    /// points at, else the file itself.
+    pub fn icon(&self) -> IconSource {
+        let icon = self.link.as_ref().and_then(|link| link.icon.as_ref());
+        if let Some((file, index)) = icon {
+            return IconSource {
+                index: *index,
+                ..IconSource::new(file)
+            };
+        }
+
+        IconSource::new(self.target().unwrap_or_else(|| self.path.clone()))
+    }
+
+    // shortcuts with the same key are copies of each other
+    fn duplicate_key(&self) -> Option<String> {
+        let identifier = self
+            .bundle
+            .as_ref()
+            .and_then(|bundle| bundle.identifier.as_ref());
+        if let Some(identifier) = identifier {
+            return Some(identifier.to_lowercase());
+        }
+
+        let link = self.link.as_ref()?;
+        Some(format!(
+            "{}\0{}",
+            link.target.as_ref()?.to_lowercase(),
+            link.arguments.as_deref().unwrap_or_default()
+        ))
+    }
+
+    /// Shown alongside the name: what the shortcut runs, or where it is (and which
+    /// version, for apps) for anything that isn't a parsed shortcut.
+    pub fn subtitle(&self) -> String {
+        let subtitle = self.describe();
+        if self.show_source {
+            format!("{} (from {})", subtitle, self.source.display())
+        } else {
+            subtitle
+        }
+    }
+
+    fn describe(&self) -> String {
+        let version = self
+            .bundle
+            .as_ref()
+            .and_then(|bundle| bundle.version.as_ref());
+        let link = match (&self.link, version) {
+            (Some(link), _) => link,
+            (None, Some(version)) => {
+                return format!("{} - version {}", self.path.display(), version)
+            }
+            (None, None) => return self.path.display().to_string(),
+        };
+        let target = match &link.target {
+            Some(target) => target,
+            None => return link.description.clone().unwrap_or_default(),
+        };
+
+        if self.broken {
+            return format!("broken shortcut, {} is missing", target);
+        }
+
+        let command_line = match &link.arguments {
+            Some(arguments) => format!("{} {}", target, arguments),
+            None => target.clone(),
+        };
+        match &link.description {
+            Some(description) => format!("{} - {}", description, command_line),
+            None => command_line,
+        }
+    }
+}
+
+fn has_extension(path: &Path, extensions: &[String]) -> bool {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default();
+
+    extensions
         .iter()
-        .map(|path| {
-            walkdir::WalkDir::new(Path::new(
-                shellexpand::env(&path)
-                    .expect("couldn't get shortcut path")
-                    .as_ref(),
-            ))
+        .any(|ext| ext.trim_start_matches('.').eq_ignore_ascii_case(extension))
+}
+
+// everything under one shortcut_paths entry, leaving out what `ignore_paths` and the
+// entry's own patterns exclude
+fn walk_root(search: &config::Search, root: &config::ShortcutRoot) -> Vec<Shortcut> {
+    // checked when the config was loaded
+    let filter = RootFilter::new(search, root).unwrap();
+    let prefix = root.prefix.as_deref().unwrap_or_default();
+
+    walkdir::WalkDir::new(&filter.root)
+        .max_depth(root.max_depth.unwrap_or(usize::MAX))
+        .follow_links(root.follow_symlinks)
+        .into_iter()
+        // don't walk into ignored or hidden folders at all
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || ((root.include_hidden || !is_hidden(entry.path()))
+                    && !filter.excludes(entry.path()))
         })
-        .flat_map(|shortcuts_dir| {
-            shortcuts_dir
-                .into_iter()
-                .filter_map(Result::ok)
-                .filter(|x| {
-                    let path = x.path().to_str().unwrap();
-                    let lowercase = path.to_lowercase();
-                    let ignored = config
-                        .search
-                        .ignore_paths
-                        .iter()
-                        .map(|ignore_str| {
-                            shellexpand::env(&ignore_str)
-                                .expect("couldn't get shortcut ignore dir")
-                                .to_lowercase()
-                        })
-                        .any(|ignore_dir| lowercase.contains(&ignore_dir));
-
-                    !ignored && (lowercase.ends_with(".lnk") || lowercase.ends_with(".url"))
-                })
-                .map(|x| x.path().to_owned())
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            entry.depth() > 0
+                && has_extension(entry.path(), &root.extensions)
+                && filter.includes(entry.path())
+        })
+        .filter_map(|entry| {
+            let link = link::read(entry.path()).unwrap_or_else(|e| {
+                println!("couldn't read shortcut {}: {}", entry.path().display(), e);
+                None
+            });
+            let bundle = bundle::read(entry.path()).unwrap_or_else(|e| {
+                println!("couldn't read app info {}: {}", entry.path().display(), e);
+                None
+            });
+
+            let name = match bundle
+                .as_ref()
+                .and_then(|bundle| bundle.display_name.clone())
+            {
+                Some(name) => name,
+                None => entry.path().file_stem()?.to_str()?.to_string(),
+            };
+            let keywords = bundle
+                .iter()
+                .filter_map(|bundle| bundle.identifier.clone())
+                .collect();
+
+            Some(Shortcut {
+                name: format!("{}{}", prefix, name),
+                keywords,
+                path: entry.into_path(),
+                broken: link.as_ref().is_some_and(Link::is_broken),
+                link,
+                bundle,
+                source: filter.root.clone(),
+                show_source: false,
+            })
         })
         .collect()
 }
 
-#[cfg(target_os = "macos")]
-pub fn get_shortcuts(config: &Config) -> Vec<PathBuf> {
-    config
+pub fn get_shortcuts(config: &Config) -> Vec<Shortcut> {
+    let shortcuts: Vec<Shortcut> = config
         .search
         .shortcut_paths
         .iter()
-        .map(|path| {
-            walkdir::WalkDir::new(Path::new(
-                shellexpand::env(&path)
-                    .expect("couldn't get shortcut path")
-                    .as_ref(),
-            ))
-            // arbitrary limit to prevent long load times with apps that store stuff incorrectly
-            // (looking at you unity)
-            .max_depth(5)
-        })
-        .flat_map(|shortcuts_dir| {
-            shortcuts_dir
-                .into_iter()
-                .filter_map(Result::ok)
-                .filter(|de| {
-                    let path = de.path();
-                    let file_name = path
-                        .file_name()
-                        .and_then(|s| s.to_str())
-                        .unwrap_or_default();
-
-                    !file_name.starts_with('.') && file_name.ends_with(".app")
-                })
-                .map(|de| de.path().to_path_buf())
+        .flat_map(|path| walk_root(&config.search, &path.root()))
+        .collect();
+
+    // the same app is often in both the user's and the shared start menu, or in both
+    // ~/Applications and /Applications. keep the copy from the user's own folders, then
+    // the first one by the order of shortcut_paths
+    let home = directories::BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+    let in_home = |shortcut: &Shortcut| {
+        home.as_ref()
+            .is_some_and(|home| shortcut.source.starts_with(home))
+    };
+
+    let mut kept: HashMap<String, usize> = HashMap::new();
+    let mut copies: HashMap<String, usize> = HashMap::new();
+    for (i, shortcut) in shortcuts.iter().enumerate() {
+        let key = match shortcut.duplicate_key() {
+            Some(key) => key,
+            None => continue,
+        };
+
+        *copies.entry(key.clone()).or_default() += 1;
+        let best = kept.entry(key).or_insert(i);
+        if in_home(shortcut) && !in_home(&shortcuts[*best]) {
+            *best = i;
+        }
+    }
+
+    let show_duplicates = config.search.show_duplicates;
+    shortcuts
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, mut shortcut)| match shortcut.duplicate_key() {
+            Some(key) if copies[&key] > 1 => {
+                shortcut.show_source = show_duplicates;
+                (show_duplicates || kept[&key] == i).then_some(shortcut)
+            }
+            _ => Some(shortcut),
         })
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundle_names() {
+        // made by tests/fixtures/bundles/generate.py
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/bundles");
+        let root = config::ShortcutRoot {
+            path: root.to_string_lossy().into_owned(),
+            max_depth: Some(1),
+            extensions: vec!["app".to_string()],
+            prefix: Some("mac: ".to_string()),
+            ..config::ShortcutRoot::default()
+        };
+
+        let mut shortcuts: Vec<(String, Vec<String>)> =
+            walk_root(&config::Search::default(), &root)
+                .into_iter()
+                .map(|shortcut| (shortcut.name, shortcut.keywords))
+                .collect();
+        shortcuts.sort();
+
+        let keywords = |identifier: &str| vec![identifier.to_string()];
+        assert_eq!(
+            shortcuts,
+            vec![
+                (
+                    "mac: Binary Name".to_string(),
+                    keywords("com.example.binary")
+                ),
+                ("mac: Broken".to_string(), vec![]),
+                (
+                    "mac: Display Name".to_string(),
+                    keywords("com.example.display")
+                ),
+                ("mac: Empty".to_string(), vec![]),
+                // no CFBundleDisplayName or CFBundleName
+                ("mac: Stem".to_string(), keywords("com.example.stem")),
+            ]
+        );
+    }
+
+    #[test]
+    fn broken_shortcuts() {
+        // made by tests/fixtures/links/generate.py
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/links");
+        let root = config::ShortcutRoot {
+            path: root.to_string_lossy().into_owned(),
+            extensions: vec!["lnk".to_string(), "url".to_string()],
+            include: vec![
+                "local.lnk".to_string(),
+                "relative.lnk".to_string(),
+                "site.url".to_string(),
+            ],
+            ..config::ShortcutRoot::default()
+        };
+
+        let mut shortcuts = walk_root(&config::Search::default(), &root);
+        shortcuts.sort_by(|a, b| a.name.cmp(&b.name));
+        let broken: Vec<(&str, bool)> = shortcuts
+            .iter()
+            .map(|shortcut| (shortcut.name.as_str(), shortcut.broken))
+            .collect();
+        assert_eq!(
+            broken,
+            [("local", true), ("relative", false), ("site", false)]
+        );
+        assert!(shortcuts[0].subtitle().starts_with("broken shortcut, "));
+        assert!(!shortcuts[1].subtitle().starts_with("broken shortcut, "));
+    }
+}
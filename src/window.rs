@@ -0,0 +1,261 @@
+// where the window goes and how big it is. sizes in [window] are logical pixels or a
+// percentage of the monitor's work area, and `position` is relative to the monitor under
+// the cursor (or the one with the focused window), worked out again every time it opens
+
+use egui::{Pos2, Vec2};
+
+use crate::config;
+
+pub const TITLE: &str = "tistow";
+
+// what percentages go by when the monitor can't be found
+const FALLBACK_SIZE: Vec2 = Vec2::new(1920.0, 1080.0);
+
+#[derive(Clone, Copy, Debug)]
+pub struct Monitor {
+    // the work area (without the taskbar) in physical pixels
+    pub origin: Pos2,
+    pub size: Vec2,
+    // physical pixels per logical pixel
+    pub scale: f32,
+}
+
+#[cfg(target_os = "windows")]
+pub fn active_monitor(monitor: config::Monitor) -> Option<Monitor> {
+    use windows::Win32::{
+        Foundation::POINT,
+        Graphics::Gdi::{
+            GetMonitorInfoW, MonitorFromPoint, MonitorFromWindow, MONITORINFO,
+            MONITOR_DEFAULTTONEAREST,
+        },
+        UI::{
+            HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI},
+            WindowsAndMessaging::{GetCursorPos, GetForegroundWindow},
+        },
+    };
+
+    unsafe {
+        let handle = match monitor {
+            config::Monitor::Cursor => {
+                let mut cursor = POINT::default();
+                GetCursorPos(&mut cursor).ok().ok()?;
+                MonitorFromPoint(cursor, MONITOR_DEFAULTTONEAREST)
+            }
+            // tistow is hidden when the hotkey is pressed, so this is whatever the user
+            // was in
+            config::Monitor::Focus => {
+                MonitorFromWindow(GetForegroundWindow(), MONITOR_DEFAULTTONEAREST)
+            }
+        };
+
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        GetMonitorInfoW(handle, &mut info).ok().ok()?;
+
+        let (mut dpi, mut dpi_y) = (96, 96);
+        GetDpiForMonitor(handle, MDT_EFFECTIVE_DPI, &mut dpi, &mut dpi_y).ok();
+
+        let work = info.rcWork;
+        Some(Monitor {
+            origin: Pos2::new(work.left as f32, work.top as f32),
+            size: Vec2::new(
+                (work.right - work.left) as f32,
+                (work.bottom - work.top) as f32,
+            ),
+            scale: dpi as f32 / 96.0,
+        })
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn active_monitor(_monitor: config::Monitor) -> Option<Monitor> {
+    use core_graphics::{
+        display::CGDisplay,
+        event::CGEvent,
+        event_source::{CGEventSource, CGEventSourceStateID},
+    };
+
+    // finding the focused window's screen needs the accessibility api, so both go by
+    // the cursor here
+    let source = CGEventSource::new(CGEventSourceStateID::CombinedSessionState).ok()?;
+    let cursor = CGEvent::new(source).ok()?.location();
+
+    let display = CGDisplay::active_displays()
+        .ok()?
+        .into_iter()
+        .map(CGDisplay::new)
+        .find(|display| {
+            let bounds = display.bounds();
+            cursor.x >= bounds.origin.x
+                && cursor.x < bounds.origin.x + bounds.size.width
+                && cursor.y >= bounds.origin.y
+                && cursor.y < bounds.origin.y + bounds.size.height
+        })
+        .unwrap_or_else(CGDisplay::main);
+
+    // bounds are in points, the display mode knows how many pixels that is on retina
+    let bounds = display.bounds();
+    let scale = display.display_mode().map_or(1.0, |mode| {
+        mode.pixel_width() as f64 / mode.width().max(1) as f64
+    });
+
+    Some(Monitor {
+        origin: Pos2::new(
+            (bounds.origin.x * scale) as f32,
+            (bounds.origin.y * scale) as f32,
+        ),
+        size: Vec2::new(
+            (bounds.size.width * scale) as f32,
+            (bounds.size.height * scale) as f32,
+        ),
+        scale: scale as f32,
+    })
+}
+
+/// Moves the window, which is already open by the time the hotkey is pressed.
+#[cfg(target_os = "windows")]
+pub fn move_to(pos: Pos2) {
+    use windows::{
+        core::PCWSTR,
+        Win32::{
+            Foundation::HWND,
+            UI::WindowsAndMessaging::{
+                FindWindowW, GetWindowThreadProcessId, SetWindowPos, SWP_NOACTIVATE, SWP_NOSIZE,
+                SWP_NOZORDER,
+            },
+        },
+    };
+
+    let title: Vec<u16> = TITLE.encode_utf16().chain(Some(0)).collect();
+    unsafe {
+        let hwnd = FindWindowW(PCWSTR::null(), PCWSTR(title.as_ptr()));
+        let mut pid = 0;
+        GetWindowThreadProcessId(hwnd, &mut pid);
+        if hwnd.0 == 0 || pid != std::process::id() {
+            println!("couldn't find the window to move it");
+            return;
+        }
+
+        SetWindowPos(
+            hwnd,
+            HWND(0),
+            pos.x as i32,
+            pos.y as i32,
+            0,
+            0,
+            SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE,
+        );
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn move_to(_pos: Pos2) {
+    // the eframe fork can't move a window after it's created, so it stays on the monitor
+    // that was active when tistow started
+}
+
+// the window with its full height, in logical pixels
+fn full_size(window: &config::Window, monitor: Option<&Monitor>) -> Vec2 {
+    let available = monitor.map_or(FALLBACK_SIZE, |monitor| monitor.size / monitor.scale);
+
+    // checked when the config was loaded
+    Vec2::new(
+        window.width.resolve(available.x).unwrap(),
+        window.height.resolve(available.y).unwrap(),
+    )
+}
+
+/// The window's size in logical pixels. With auto_height it's just tall enough for
+/// what was drawn on the last frame, `content_height`.
+pub fn size(
+    window: &config::Window,
+    monitor: Option<&Monitor>,
+    content_height: Option<f32>,
+) -> Vec2 {
+    let full = full_size(window, monitor);
+    match content_height {
+        Some(height) if window.auto_height => Vec2::new(full.x, height.min(full.y)),
+        _ => full,
+    }
+}
+
+/// Where the top left corner goes, in physical pixels. It's placed as if it had its full
+/// height, so it grows downwards with auto_height instead of jumping around.
+pub fn position(window: &config::Window, monitor: Option<&Monitor>) -> Pos2 {
+    let monitor = match monitor {
+        Some(monitor) if window.position != config::Position::Absolute => monitor,
+        _ => return Pos2::new(window.x as f32, window.y as f32),
+    };
+
+    let size = full_size(window, Some(monitor)) * monitor.scale;
+    let x = monitor.origin.x + (monitor.size.x - size.x) / 2.0;
+    let y = match window.position {
+        config::Position::TopThird => (monitor.size.y / 3.0 - size.y / 2.0).max(0.0),
+        _ => (monitor.size.y - size.y) / 2.0,
+    };
+
+    Pos2::new(x, monitor.origin.y + y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONITOR: Monitor = Monitor {
+        origin: Pos2::new(1920.0, 0.0),
+        size: Vec2::new(2000.0, 1200.0),
+        scale: 2.0,
+    };
+
+    #[test]
+    fn placement() {
+        // x and y are ignored unless the position says so, old configs all have them
+        let window = config::Window {
+            x: 10,
+            ..Default::default()
+        };
+        assert_eq!(position(&window, Some(&MONITOR)), Pos2::new(2280.0, 280.0));
+        // without a monitor there's nothing to center on
+        assert_eq!(position(&window, None), Pos2::new(10.0, 380.0));
+
+        let window = config::Window {
+            position: config::Position::TopThird,
+            ..window
+        };
+        assert_eq!(position(&window, Some(&MONITOR)), Pos2::new(2280.0, 80.0));
+
+        let window = config::Window {
+            position: config::Position::Absolute,
+            ..window
+        };
+        assert_eq!(position(&window, Some(&MONITOR)), Pos2::new(10.0, 380.0));
+    }
+
+    #[test]
+    fn sizes() {
+        let window = config::Window {
+            width: config::Length::Percent("50%".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(size(&window, Some(&MONITOR), None), Vec2::new(500.0, 320.0));
+        assert_eq!(
+            size(&window, Some(&MONITOR), Some(100.0)),
+            Vec2::new(500.0, 100.0)
+        );
+        assert_eq!(
+            size(&window, Some(&MONITOR), Some(1000.0)),
+            Vec2::new(500.0, 320.0)
+        );
+
+        let window = config::Window {
+            auto_height: false,
+            ..window
+        };
+        assert_eq!(
+            size(&window, Some(&MONITOR), Some(100.0)),
+            Vec2::new(500.0, 320.0)
+        );
+    }
+}
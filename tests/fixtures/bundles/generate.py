@@ -0,0 +1,49 @@
+# writes the .app bundles for the tests in src/bundle.rs and src/util.rs. run it from
+# this folder: python3 generate.py
+import os
+import plistlib
+
+bundles = {
+    # every key, as xml
+    "Display.app": (
+        plistlib.FMT_XML,
+        {
+            "CFBundleDisplayName": "Display Name",
+            "CFBundleName": "Display",
+            "CFBundleIdentifier": "com.example.display",
+            "CFBundleShortVersionString": "2.1",
+            "CFBundleVersion": "2104",
+            "CFBundleIconFile": "AppIcon",
+            "CFBundleExecutable": "display",
+        },
+    ),
+    # a blank display name and only the build number, as a binary plist
+    "Binary.app": (
+        plistlib.FMT_BINARY,
+        {
+            "CFBundleDisplayName": "  ",
+            "CFBundleName": "Binary Name",
+            "CFBundleIdentifier": "com.example.binary",
+            "CFBundleVersion": "7",
+            "CFBundleExecutable": "binary",
+            "LSUIElement": True,
+        },
+    ),
+    # no names at all, so it goes by the folder name
+    "Stem.app": (plistlib.FMT_BINARY, {"CFBundleIdentifier": "com.example.stem"}),
+    # a folder that isn't an app, even with an Info.plist
+    "NotABundle": (plistlib.FMT_XML, {"CFBundleName": "Not a bundle"}),
+}
+
+for name, (fmt, info) in bundles.items():
+    os.makedirs(os.path.join(name, "Contents"), exist_ok=True)
+    with open(os.path.join(name, "Contents", "Info.plist"), "wb") as file:
+        plistlib.dump(info, file, fmt=fmt)
+
+# an app without an Info.plist, and one whose Info.plist isn't a plist
+os.makedirs(os.path.join("Empty.app", "Contents", "MacOS"), exist_ok=True)
+with open(os.path.join("Empty.app", "Contents", "MacOS", "empty"), "wb") as file:
+    file.write(b"")
+os.makedirs(os.path.join("Broken.app", "Contents"), exist_ok=True)
+with open(os.path.join("Broken.app", "Contents", "Info.plist"), "wb") as file:
+    file.write(b"<?xml version=\"1.0\"?><plist><dict><key>CFBundleName")
@@ -0,0 +1,195 @@
+# writes the .lnk and .url fixtures for the tests in src/link.rs, laid out as in
+# [MS-SHLLINK]. run it from this folder: python3 generate.py
+import struct
+
+CLSID = bytes.fromhex("0114020000000000c000000000000046")
+MY_COMPUTER = bytes.fromhex("e04fd020ea3a6910a2d808002b30309d")
+
+HAS_LINK_TARGET_ID_LIST = 0x1
+HAS_LINK_INFO = 0x2
+HAS_NAME = 0x4
+HAS_RELATIVE_PATH = 0x8
+HAS_WORKING_DIR = 0x10
+HAS_ARGUMENTS = 0x20
+HAS_ICON_LOCATION = 0x40
+IS_UNICODE = 0x80
+HAS_EXP_STRING = 0x200
+HAS_DARWIN_ID = 0x1000
+HAS_EXP_ICON = 0x4000
+
+
+def header(flags, icon_index=0):
+    return (
+        struct.pack("<I16sII", 0x4C, CLSID, flags, 0x20)
+        + bytes(24)  # creation, access and write times
+        + struct.pack("<IiIHHII", 0, icon_index, 1, 0, 0, 0, 0)
+    )
+
+
+def id_list():
+    item = struct.pack("<HBB16s", 20, 0x1F, 0x50, MY_COMPUTER)
+    return struct.pack("<H", len(item) + 2) + item + struct.pack("<H", 0)
+
+
+def ansi(text):
+    return text.encode("cp1252") + b"\0"
+
+
+def unicode(text):
+    return text.encode("utf-16-le") + b"\0\0"
+
+
+def local_link_info(base, suffix):
+    # the 0x24 byte header, with unicode copies of the paths
+    volume = struct.pack("<IIII", 0x11, 3, 0x1234ABCD, 0x10) + b"\0"
+    parts = [volume, ansi(base), ansi(suffix), unicode(base), unicode(suffix)]
+    offsets = []
+    offset = 0x24
+    for part in parts:
+        offsets.append(offset)
+        offset += len(part)
+    head = struct.pack(
+        "<IIIIIIIII",
+        offset,
+        0x24,
+        0x1,  # VolumeIDAndLocalBasePath
+        offsets[0],
+        offsets[1],
+        0,
+        offsets[2],
+        offsets[3],
+        offsets[4],
+    )
+    return head + b"".join(parts)
+
+
+def network_link_info(share, suffix):
+    # the old 0x1c byte header, ansi only
+    net_name = ansi(share)
+    network = struct.pack("<IIIII", 0x14 + len(net_name), 0, 0x14, 0, 0) + net_name
+    suffix = ansi(suffix)
+    size = 0x1C + len(network) + len(suffix)
+    head = struct.pack(
+        "<IIIIIII",
+        size,
+        0x1C,
+        0x2,  # CommonNetworkRelativeLink
+        0,
+        0,
+        0x1C,
+        0x1C + len(network),
+    )
+    return head + network + suffix
+
+
+def strings(values, wide):
+    data = b""
+    for value in values:
+        if wide:
+            data += struct.pack("<H", len(value.encode("utf-16-le")) // 2)
+            data += value.encode("utf-16-le")
+        else:
+            data += struct.pack("<H", len(value)) + value.encode("cp1252")
+    return data
+
+
+def path_block(signature, ansi_path, unicode_path):
+    ansi_part = ansi_path.encode("cp1252").ljust(260, b"\0")
+    unicode_part = unicode_path.encode("utf-16-le").ljust(520, b"\0")
+    return struct.pack("<II", 0x314, signature) + ansi_part + unicode_part
+
+
+END = struct.pack("<I", 0)
+
+fixtures = {}
+
+# a local program with a unicode path, whose ansi copy can't spell it
+fixtures["local.lnk"] = (
+    header(
+        HAS_LINK_TARGET_ID_LIST
+        | HAS_LINK_INFO
+        | HAS_NAME
+        | HAS_WORKING_DIR
+        | HAS_ARGUMENTS
+        | HAS_ICON_LOCATION
+        | IS_UNICODE,
+        icon_index=3,
+    )
+    + id_list()
+    + local_link_info("C:\\Program Files\\Caf\u00e9\\", "app.exe")
+    + strings(
+        [
+            "Caf\u00e9 \u2615",
+            "C:\\Program Files\\Caf\u00e9",
+            '--open "two words"',
+            "%TISTOW_NOT_SET%\\icons.dll",
+        ],
+        wide=True,
+    )
+    + END
+)
+# the ansi copy says C:\Program Files\Caf?\app.exe
+fixtures["local.lnk"] = fixtures["local.lnk"].replace(
+    "Caf\u00e9\\\0".encode("cp1252"), b"Caf?\\\0"
+)
+
+# only an environment block, with an icon block that has no unicode copy
+fixtures["environment.lnk"] = (
+    header(HAS_ARGUMENTS | IS_UNICODE | HAS_EXP_STRING | HAS_EXP_ICON)
+    + strings(["/quiet"], wide=True)
+    + path_block(0xA0000001, "%TISTOW_FIXTURE_DIR%\\tool.exe", "%TISTOW_FIXTURE_DIR%\\tool.exe")
+    + path_block(0xA0000007, "%TISTOW_FIXTURE_DIR%\\tool.ico", "")
+    + END
+)
+
+# what an msi installs: a darwin id for windows installer to resolve, no target
+fixtures["advertised.lnk"] = (
+    header(HAS_NAME | HAS_ICON_LOCATION | IS_UNICODE | HAS_DARWIN_ID)
+    + strings(["Advertised app", "C:\\Windows\\Installer\\{GUID}\\app.ico"], wide=True)
+    + path_block(0xA0000006, "w_=Kj@?Ub$]Sv=Iy4'R>app", "w_=Kj@?Ub$]Sv=Iy4'R>app")
+    + END
+)
+
+# a file on a share, with ansi strings
+fixtures["network.lnk"] = (
+    header(HAS_LINK_INFO | HAS_WORKING_DIR | HAS_ARGUMENTS)
+    + network_link_info("\\\\server\\share", "docs\\report.docx")
+    + strings(["\\\\server\\share\\docs", "/readonly"], wide=False)
+    + END
+)
+
+# a relative path only, to notes.txt next to it
+fixtures["relative.lnk"] = (
+    header(HAS_RELATIVE_PATH | IS_UNICODE) + strings(["notes.txt"], wide=True) + END
+)
+
+# cut off in the middle of the arguments
+fixtures["truncated.lnk"] = fixtures["local.lnk"][: len(fixtures["local.lnk"]) - 80]
+
+fixtures["notes.txt"] = b"the target of relative.lnk\n"
+
+fixtures["site.url"] = (
+    "[DEFAULT]\r\n"
+    "BASEURL=https://wrong.example.com/\r\n"
+    "[internetshortcut]\r\n"
+    "url=https://example.com/docs?a=1&b=2\r\n"
+    "IDList=\r\n"
+    "ICONFILE=C:\\Program Files\\Browser\\browser.exe\r\n"
+    "IconIndex = 2\r\n"
+    "WorkingDirectory=C:\\Users\\me\r\n"
+    "[InternetShortcut.A]\r\n"
+    "URL=https://also-wrong.example.com/\r\n"
+    "[{000214A0-0000-0000-C000-000000000046}]\r\n"
+    "Prop3=19,11\r\n"
+).encode("ascii")
+
+fixtures["steam.url"] = (
+    "[InternetShortcut]\n"
+    "URL=steam://rungameid/570\n"
+    "IconFile=C:\\Program Files (x86)\\Steam\\steam\\games\\dota.ico\n"
+    "IconIndex=not a number\n"
+).encode("ascii")
+
+for name, data in fixtures.items():
+    with open(name, "wb") as file:
+        file.write(data)